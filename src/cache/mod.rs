@@ -8,18 +8,62 @@ use async_trait::async_trait;
 pub trait CacheLayer {
     async fn get(&self, key: &str) -> Result<Option<Memory>>;
     async fn set(&self, key: &str, memory: &Memory, ttl_seconds: Option<u64>) -> Result<()>;
-    async fn search_cached(&self, request: &SearchRequest) -> Result<Vec<Memory>>;
+    async fn search_cached(&self, request: &SearchRequest) -> Result<CachedSearch>;
     async fn invalidate(&self, key: &str) -> Result<()>;
     async fn get_stats(&self) -> Result<CacheStats>;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Keyword matches from the Redis cache
+#[derive(Debug, Clone, Default)]
+pub struct CachedSearch {
+    pub memories: Vec<Memory>,
+    /// The search ran out of its time budget, so matches may be missing
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CacheStats {
     pub total_keys: u64,
     pub memory_usage_bytes: u64,
     pub hit_rate: f32,
     pub miss_rate: f32,
     pub avg_retrieval_time_ms: f32,
+    /// Uncompressed / stored bytes over all thought values written; 1.0 without compression
+    pub compression_ratio: f32,
+    /// Cached embeddings (shared by all instances) tracked for LRU eviction
+    pub embedding_cache_entries: u64,
+    /// `EMBEDDING_CACHE_MAX_ENTRIES`, when the embedding cache is bounded
+    pub embedding_cache_max_entries: Option<u64>,
+}
+
+/// Key count and memory footprint of one instance's `<instance>:*` keys
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InstanceStats {
+    pub instance_id: String,
+    pub thought_count: u64,
+    pub total_keys: u64,
+    /// Sum of `MEMORY USAGE` over the instance's keys
+    pub memory_usage_bytes: u64,
+}
+
+/// An entry of the `um:instances` registry
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InstanceInfo {
+    pub instance_id: String,
+    /// Time of the instance's most recent thought write, if recorded
+    pub last_write: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InstancesResult {
+    pub instances: Vec<InstanceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InstanceStatsResult {
+    pub instances: Vec<InstanceStats>,
 }
 
+use chrono::{DateTime, Utc};
+use rmcp::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
\ No newline at end of file
@@ -1,68 +1,775 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use async_trait::async_trait;
-use deadpool_redis::Pool;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use deadpool_redis::{Connection, Pool, PoolError};
 use redis::AsyncCommands;
-use crate::cache::{CacheLayer, CacheStats};
-use crate::error::Result;
-use crate::models::{Memory, SearchRequest};
+use crate::cache::{CacheLayer, CachedSearch, CacheStats, InstanceInfo, InstanceStats};
+use crate::config::{KeyScheme, RedisConfig, DEFAULT_EMBEDDING_MODEL};
+use crate::error::{Result, UnifiedRagError};
+use crate::models::{Memory, SearchRequest, StoreResult, TagCooccurrence, TagCooccurrenceResult, extra_matches};
+use crate::text;
 use md5;
 
 #[derive(Clone)]
 pub struct RedisCache {
     pool: Arc<Pool>,
     prefix: String,
+    legacy_md5_keys: bool,
+    retry_attempts: u32,
+    compress: bool,
+    search_cache: bool,
+    search_cache_ttl_secs: u64,
+    idempotency_ttl_secs: u64,
+    embedding_cache_max_entries: Option<u64>,
+    /// Cap on keyword search results, below any requested limit
+    max_results: usize,
+    search_budget: Option<Duration>,
+    keys: Arc<KeyScheme>,
+    /// Namespaces cached embeddings; `None` for the default model, whose keys predate namespacing
+    embedding_model: Option<String>,
+}
+
+/// Set of every instance id that has written a thought to this Redis
+const INSTANCES_KEY: &str = "um:instances";
+
+/// Hash of instance id -> RFC 3339 time of its last thought write
+const INSTANCE_LAST_WRITE_KEY: &str = "um:instances:last_write";
+
+/// Sorted set of embedding cache keys scored by last use (unix ms), for LRU eviction
+const EMBEDDING_LRU_KEY: &str = "um:embedding_lru";
+
+/// Leading byte of a gzip-compressed thought value. Plain values are JSON and
+/// start with `{`, so data written before compression was enabled still reads.
+const COMPRESSED_HEADER: u8 = 0x01;
+
+/// Pool errors worth retrying: checkout timeouts and connection-level failures.
+/// Anything else (closed pool, hook errors) won't fix itself.
+fn is_transient(e: &PoolError) -> bool {
+    match e {
+        PoolError::Timeout(_) => true,
+        PoolError::Backend(e) => {
+            e.is_io_error() || e.is_connection_refusal() || e.is_connection_dropped() || e.is_timeout()
+        }
+        _ => false,
+    }
+}
+
+/// Relation set entry pointing at `other`; ids are UUIDs, so the first `:` ends the id
+fn relation_member(other: &str, relation: &str) -> String {
+    format!("{}:{}", other, relation)
 }
 
 impl RedisCache {
-    pub fn new(pool: Arc<Pool>, instance_id: &str) -> Self {
+    pub fn new(pool: Arc<Pool>, instance_id: &str, config: &RedisConfig) -> Self {
         Self {
             pool,
             prefix: instance_id.to_string(),
+            legacy_md5_keys: config.legacy_md5_keys,
+            retry_attempts: config.retry_attempts,
+            compress: config.compress,
+            search_cache: config.search_cache,
+            search_cache_ttl_secs: config.search_cache_ttl_secs,
+            embedding_cache_max_entries: config.embedding_cache_max_entries,
+            idempotency_ttl_secs: config.idempotency_ttl_secs,
+            max_results: usize::MAX,
+            search_budget: (config.search_budget_ms > 0).then(|| Duration::from_millis(config.search_budget_ms)),
+            keys: Arc::new(config.key_scheme.clone()),
+            embedding_model: None,
+        }
+    }
+    
+    /// Keep cached embeddings of `model` apart from other models' vectors for the same text
+    pub fn with_embedding_model(mut self, model: &str) -> Self {
+        self.embedding_model = (model != DEFAULT_EMBEDDING_MODEL).then(|| model.to_string());
+        self
+    }
+    
+    /// Never return more than `max` keyword search results
+    pub fn with_max_results(mut self, max: usize) -> Self {
+        self.max_results = max.max(1);
+        self
+    }
+    
+    /// Check out a connection, retrying transient pool errors with backoff.
+    ///
+    /// Only the checkout is retried; a failing command on a live connection
+    /// surfaces immediately. Broken connections are dropped by the pool, so a
+    /// retry dials a fresh one.
+    async fn conn(&self) -> Result<Connection> {
+        let mut delay = std::time::Duration::from_millis(100);
+        let mut attempt = 0;
+        
+        loop {
+            match self.pool.get().await {
+                Ok(conn) => {
+                    if attempt > 0 {
+                        tracing::info!("Reconnected to Redis after {} retries", attempt);
+                    }
+                    return Ok(conn);
+                }
+                Err(e) if attempt < self.retry_attempts && is_transient(&e) => {
+                    attempt += 1;
+                    tracing::warn!(
+                        "Redis connection failed ({}), reconnecting (attempt {}/{}) in {:?}",
+                        e, attempt, self.retry_attempts, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    
+    /// A cache over the same pool keyed under another instance's prefix
+    pub fn for_instance(&self, instance_id: &str) -> Self {
+        Self {
+            prefix: instance_id.to_string(),
+            ..self.clone()
+        }
+    }
+    
+    /// Load several thoughts in one MGET, one entry per `(instance_id, thought_id)`
+    /// in input order. Unlike `get`, this doesn't count as an access.
+    pub async fn get_many(&self, ids: &[(String, String)]) -> Result<Vec<Option<Memory>>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let keys: Vec<String> = ids
+            .iter()
+            .map(|(instance_id, id)| self.for_instance(instance_id).make_thought_key(id))
+            .collect();
+        let mut conn = self.conn().await?;
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?;
+        
+        values
+            .into_iter()
+            .map(|value| value.map(|bytes| Self::decode(&bytes)).transpose())
+            .collect()
+    }
+    
+    /// Re-key a thought under `target`'s prefix: the thought and metadata keys are
+    /// written there and it joins `target`'s tag/chain indexes, then the entries
+    /// under this prefix are removed.
+    pub async fn move_thought(&self, key: &str, memory: &Memory, target: &RedisCache) -> Result<()> {
+        target.set(key, memory, None).await?;
+        self.invalidate(key).await
+    }
+    
+    /// Rewrite a thought whose tags changed: the stored value and the metadata's
+    /// `tags` are replaced and the thought joins/leaves the matching tag index sets,
+    /// all in one MULTI so the indexes can't drift from the thought
+    pub async fn update_tags(&self, key: &str, memory: &Memory, added: &[String], removed: &[String]) -> Result<()> {
+        let mut conn = self.conn().await?;
+        let value = self.encode(&serde_json::to_string(memory)?)?;
+        
+        let metadata_key = self.make_metadata_key(key);
+        let metadata: Option<String> = conn.get(&metadata_key).await?;
+        let metadata = match metadata {
+            Some(json) => {
+                let mut metadata: serde_json::Value = serde_json::from_str(&json)?;
+                metadata["tags"] = serde_json::json!(memory.metadata.tags);
+                Some(metadata.to_string())
+            }
+            None => None,
+        };
+        
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.cmd("SET").arg(self.make_thought_key(key)).arg(&value).arg("KEEPTTL").ignore();
+        if let Some(metadata) = metadata {
+            pipe.set(&metadata_key, metadata).ignore();
         }
+        for tag in added {
+            pipe.sadd(self.make_tag_key(tag), key).ignore();
+        }
+        for tag in removed {
+            pipe.srem(self.make_tag_key(tag), key).ignore();
+        }
+        pipe.query_async::<()>(&mut conn).await?;
+        
+        Ok(())
     }
     
-    fn make_key(&self, key: &str) -> String {
-        format!("{}{}", self.prefix, key)
+    /// Serialize a thought value, gzipping it behind `COMPRESSED_HEADER` when enabled
+    fn encode(&self, json: &str) -> Result<Vec<u8>> {
+        if !self.compress {
+            return Ok(json.as_bytes().to_vec());
+        }
+        
+        let mut encoder = GzEncoder::new(vec![COMPRESSED_HEADER], Compression::default());
+        encoder
+            .write_all(json.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map_err(|e| UnifiedRagError::Compression(e.to_string()))
+    }
+    
+    /// Parse a thought value written by `encode`, compressed or not
+    fn decode(bytes: &[u8]) -> Result<Memory> {
+        match bytes.split_first() {
+            Some((&COMPRESSED_HEADER, compressed)) => {
+                let mut json = String::new();
+                GzDecoder::new(compressed)
+                    .read_to_string(&mut json)
+                    .map_err(|e| UnifiedRagError::Compression(e.to_string()))?;
+                Ok(serde_json::from_str(&json)?)
+            }
+            _ => Ok(serde_json::from_slice(bytes)?),
+        }
+    }
+    
+    /// Apply a search request's category, tag and instance filters and its
+    /// excluded ids to a cached thought
+    fn matches_filters(memory: &Memory, request: &SearchRequest) -> bool {
+        if request.exclude_ids.contains(&memory.id) {
+            return false;
+        }
+        
+        if let Some(ref category) = request.category_filter {
+            let matches = memory.metadata.category.as_deref()
+                .is_some_and(|c| request.category_match_mode.matches(category, c));
+            if !matches {
+                return false;
+            }
+        }
+        
+        if let Some(ref tags_filter) = request.tags_filter {
+            let matched = tags_filter.iter().filter(|tag| memory.metadata.tags.contains(tag)).count();
+            if matched < request.tag_min_match.unwrap_or(1) {
+                return false;
+            }
+        }
+        
+        if let Some(ref instance_filter) = request.instance_filter {
+            if !instance_filter.contains(&memory.instance_id) {
+                return false;
+            }
+        }
+        
+        if let Some(ref chain_id) = request.chain_filter {
+            if memory.metadata.chain_id.as_ref() != Some(chain_id) {
+                return false;
+            }
+        }
+        
+        if let Some(ref extra) = request.extra_filters {
+            if !extra.iter().all(|(key, value)| extra_matches(memory.metadata.extra.get(key), value)) {
+                return false;
+            }
+        }
+        
+        // Every word must occur, as with Qdrant's word-tokenized, lowercased text index
+        if let Some(ref text) = request.text_match {
+            if crate::text::keyword_overlap(text, &memory.content) < 1.0 {
+                return false;
+            }
+        }
+        
+        true
+    }
+    
+    fn make_compression_key(&self) -> String {
+        format!("{}{}compression", self.prefix, self.keys.separator)
+    }
+    
+    fn make_embedding_queue_key(&self) -> String {
+        format!("{}{}embedding_queue", self.prefix, self.keys.separator)
+    }
+    
+    /// `<prefix><sep><segment><sep><id>` under the configured key scheme
+    fn instance_key(&self, segment: &str, id: &str) -> String {
+        let sep = &self.keys.separator;
+        format!("{}{}{}{}{}", self.prefix, sep, segment, sep, id)
     }
     
     fn make_thought_key(&self, thought_id: &str) -> String {
-        format!("{}:Thoughts:{}", self.prefix, thought_id)
+        self.instance_key(&self.keys.thoughts, thought_id)
     }
     
     fn make_embedding_key(&self, content: &str) -> String {
-        let hash = format!("{:x}", md5::compute(content));
+        let hash = text::sha256_hex(text::normalize(content).as_bytes());
+        match self.embedding_model {
+            Some(ref model) => format!("um:embedding:{}:{}", model, hash),
+            None => format!("um:embedding:{}", hash),
+        }
+    }
+    
+    /// Pre-sha256 embedding key, only read when `legacy_md5_keys` is enabled
+    fn make_legacy_embedding_key(&self, content: &str) -> String {
+        let hash = format!("{:x}", md5::compute(text::normalize(content).as_bytes()));
         format!("um:embedding:{}", hash)
     }
     
     fn make_metadata_key(&self, thought_id: &str) -> String {
-        format!("{}:thought_meta:{}", self.prefix, thought_id)
+        self.instance_key(&self.keys.thought_meta, thought_id)
     }
     
     fn make_tag_key(&self, tag: &str) -> String {
-        format!("{}:tags:{}", self.prefix, tag)
+        self.instance_key(&self.keys.tags, tag)
     }
     
     fn make_chain_key(&self, chain_id: &str) -> String {
-        format!("{}:chains:{}", self.prefix, chain_id)
+        self.instance_key(&self.keys.chains, chain_id)
     }
     
     fn make_cache_key(&self, query_hash: &str) -> String {
         format!("um:cache:{}", query_hash)
     }
+    
+    fn make_import_key(&self, source: &str) -> String {
+        self.instance_key("imports", source)
+    }
+    
+    fn make_idempotency_key(&self, key: &str) -> String {
+        self.instance_key("idempotency", key)
+    }
+    
+    fn make_relations_key(&self, thought_id: &str) -> String {
+        self.instance_key("relations", thought_id)
+    }
+    
+    /// Delete every `<prefix>:*` key (thoughts, metadata, tag/chain indexes, import hashes)
+    /// and drop the instance from the registry. Other shared `um:*` keys are left alone.
+    /// Returns the number of keys removed.
+    pub async fn clear_instance(&self) -> Result<u64> {
+        let mut conn = self.conn().await?;
+        let pattern = format!("{}{}*", self.prefix, self.keys.separator);
+        let mut cursor: u64 = 0;
+        let mut removed = 0u64;
+        
+        loop {
+            let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(500)
+                .query_async(&mut conn)
+                .await?;
+            
+            if !keys.is_empty() {
+                removed += conn.del::<_, u64>(&keys).await?;
+            }
+            
+            cursor = new_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        
+        redis::pipe()
+            .srem(INSTANCES_KEY, &self.prefix)
+            .hdel(INSTANCE_LAST_WRITE_KEY, &self.prefix)
+            .query_async::<()>(&mut conn)
+            .await?;
+        
+        Ok(removed)
+    }
+    
+    /// Round-trip a PING to check Redis is reachable
+    pub async fn ping(&self) -> Result<()> {
+        let mut conn = self.conn().await?;
+        redis::cmd("PING").query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+    
+    /// Every registered instance with its last write time, sorted by id
+    pub async fn instances(&self) -> Result<Vec<InstanceInfo>> {
+        let mut conn = self.conn().await?;
+        let mut ids: Vec<String> = conn.smembers(INSTANCES_KEY).await?;
+        ids.sort();
+        let last_writes: std::collections::HashMap<String, String> = conn.hgetall(INSTANCE_LAST_WRITE_KEY).await?;
+        
+        Ok(ids
+            .into_iter()
+            .map(|instance_id| {
+                let last_write = last_writes
+                    .get(&instance_id)
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .map(|t| t.with_timezone(&chrono::Utc));
+                InstanceInfo { instance_id, last_write }
+            })
+            .collect())
+    }
+    
+    /// Per-instance key counts and memory usage for every instance in `um:instances`
+    pub async fn instance_stats(&self) -> Result<Vec<InstanceStats>> {
+        let mut conn = self.conn().await?;
+        let mut instances: Vec<String> = conn.smembers(INSTANCES_KEY).await?;
+        instances.sort();
+        drop(conn);
+        
+        let mut stats = Vec::with_capacity(instances.len());
+        for instance_id in instances {
+            stats.push(self.for_instance(&instance_id).prefix_stats().await?);
+        }
+        Ok(stats)
+    }
+    
+    /// SCAN this prefix's keys, counting thoughts and summing `MEMORY USAGE` per batch
+    async fn prefix_stats(&self) -> Result<InstanceStats> {
+        let mut conn = self.conn().await?;
+        let pattern = format!("{}{}*", self.prefix, self.keys.separator);
+        let thought_prefix = self.instance_key(&self.keys.thoughts, "");
+        let mut cursor: u64 = 0;
+        let mut stats = InstanceStats {
+            instance_id: self.prefix.clone(),
+            thought_count: 0,
+            total_keys: 0,
+            memory_usage_bytes: 0,
+        };
+        
+        loop {
+            let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(500)
+                .query_async(&mut conn)
+                .await?;
+            
+            if !keys.is_empty() {
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    pipe.cmd("MEMORY").arg("USAGE").arg(key);
+                }
+                // Keys deleted between SCAN and MEMORY USAGE come back as nil
+                let usage: Vec<Option<u64>> = pipe.query_async(&mut conn).await?;
+                stats.memory_usage_bytes += usage.into_iter().flatten().sum::<u64>();
+                stats.total_keys += keys.len() as u64;
+                stats.thought_count += keys.iter().filter(|k| k.starts_with(&thought_prefix)).count() as u64;
+            }
+            
+            cursor = new_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        
+        Ok(stats)
+    }
+    
+    /// Embedding dimension previously detected for `model`
+    pub async fn embedding_dimension(&self, model: &str) -> Result<Option<u64>> {
+        let mut conn = self.conn().await?;
+        let dimension: Option<u64> = conn.get(format!("um:embedding_dim:{}", model)).await?;
+        Ok(dimension)
+    }
+    
+    pub async fn set_embedding_dimension(&self, model: &str, dimension: u64) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.set::<_, _, ()>(format!("um:embedding_dim:{}", model), dimension).await?;
+        Ok(())
+    }
+    
+    /// Memory ids in a chain, in insertion order
+    pub async fn chain_members(&self, chain_id: &str) -> Result<Vec<String>> {
+        let mut conn = self.conn().await?;
+        let ids: Vec<String> = conn.lrange(self.make_chain_key(chain_id), 0, -1).await?;
+        Ok(ids)
+    }
+    
+    /// After `set` rewrote `key` from `previous` to `current`, drop the index entries
+    /// `set` doesn't clean up: tags `current` no longer has, and the chain entry it
+    /// pushed a second time (the later copy goes, so the chain order is kept)
+    pub async fn drop_stale_indexes(&self, key: &str, previous: &Memory, current: &Memory) -> Result<()> {
+        let mut conn = self.conn().await?;
+        for tag in &previous.metadata.tags {
+            if !current.metadata.tags.contains(tag) {
+                conn.srem::<_, _, ()>(self.make_tag_key(tag), key).await?;
+            }
+        }
+        if let Some(chain_id) = &previous.metadata.chain_id {
+            let count = if current.metadata.chain_id.as_ref() == Some(chain_id) { -1 } else { 0 };
+            conn.lrem::<_, _, ()>(self.make_chain_key(chain_id), count, key).await?;
+        }
+        Ok(())
+    }
+    
+    /// Tags most often found alongside `tag`, tallied from the metadata of the
+    /// tag's index set members and scored by the Jaccard index of the two sets
+    pub async fn tag_cooccurrence(&self, tag: &str, limit: usize) -> Result<TagCooccurrenceResult> {
+        let mut conn = self.conn().await?;
+        let ids: Vec<String> = conn.smembers(self.make_tag_key(tag)).await?;
+        
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let mut memories = 0u64;
+        for chunk in ids.chunks(256) {
+            let keys: Vec<String> = chunk.iter().map(|id| self.make_metadata_key(id)).collect();
+            let values: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?;
+            
+            // Index entries can outlive thoughts that expired via TTL
+            for value in values.into_iter().flatten() {
+                let Ok(metadata) = serde_json::from_str::<serde_json::Value>(&value) else { continue };
+                memories += 1;
+                let tags = metadata["tags"].as_array().into_iter().flatten().filter_map(|t| t.as_str());
+                for other in tags.filter(|t| *t != tag) {
+                    *counts.entry(other.to_string()).or_default() += 1;
+                }
+            }
+        }
+        
+        let mut ranked: Vec<(String, u64)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        
+        let mut pipe = redis::pipe();
+        for (other, _) in &ranked {
+            pipe.scard(self.make_tag_key(other));
+        }
+        let sizes: Vec<u64> = pipe.query_async(&mut conn).await?;
+        
+        let tag_size = ids.len() as u64;
+        let cooccurring = ranked
+            .into_iter()
+            .zip(sizes)
+            .map(|((other, count), size)| {
+                let union = (tag_size + size).saturating_sub(count).max(count);
+                TagCooccurrence {
+                    tag: other,
+                    count,
+                    score: if union == 0 { 0.0 } else { count as f64 / union as f64 },
+                }
+            })
+            .collect();
+        
+        Ok(TagCooccurrenceResult {
+            tag: tag.to_string(),
+            memories,
+            cooccurring,
+        })
+    }
+    
+    /// Size of each tag's index set, in the order given; 0 for tags nothing carries
+    pub async fn tag_index_sizes(&self, tags: &[String]) -> Result<Vec<u64>> {
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.conn().await?;
+        let mut pipe = redis::pipe();
+        for tag in tags {
+            pipe.scard(self.make_tag_key(tag));
+        }
+        let sizes: Vec<u64> = pipe.query_async(&mut conn).await?;
+        Ok(sizes)
+    }
+    
+    /// Result of an earlier store made with idempotency key `key`, if it hasn't expired
+    pub async fn idempotent_result(&self, key: &str) -> Result<Option<StoreResult>> {
+        let mut conn = self.conn().await?;
+        let json: Option<String> = conn.get(self.make_idempotency_key(key)).await?;
+        json.map(|j| serde_json::from_str(&j)).transpose().map_err(Into::into)
+    }
+    
+    /// Remember `result` under `key` for `idempotency_ttl_secs`; an existing entry wins
+    pub async fn set_idempotent_result(&self, key: &str, result: &StoreResult) -> Result<()> {
+        let mut conn = self.conn().await?;
+        redis::cmd("SET")
+            .arg(self.make_idempotency_key(key))
+            .arg(serde_json::to_string(result)?)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.idempotency_ttl_secs)
+            .query_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+    
+    /// Queue memory `id`, stored without a vector, for background embedding
+    pub async fn enqueue_embedding(&self, id: &str) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.rpush::<_, _, ()>(self.make_embedding_queue_key(), id).await?;
+        Ok(())
+    }
+    
+    /// Up to `limit` memory ids awaiting an embedding, oldest first
+    pub async fn queued_embeddings(&self, limit: usize) -> Result<Vec<String>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.conn().await?;
+        let ids: Vec<String> = conn.lrange(self.make_embedding_queue_key(), 0, limit as isize - 1).await?;
+        Ok(ids)
+    }
+    
+    /// Take memory `id` off the embedding queue once it is indexed (or gone)
+    pub async fn dequeue_embedding(&self, id: &str) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.lrem::<_, _, ()>(self.make_embedding_queue_key(), 0, id).await?;
+        Ok(())
+    }
+    
+    /// Link two thoughts under `relation`, recorded in both thoughts' relation sets
+    /// as `<other id>:<relation>`. Returns false when the link already existed.
+    pub async fn add_relation(&self, a: &str, b: &str, relation: &str) -> Result<bool> {
+        let mut conn = self.conn().await?;
+        let (added,): (u64,) = redis::pipe()
+            .atomic()
+            .sadd(self.make_relations_key(a), relation_member(b, relation))
+            .sadd(self.make_relations_key(b), relation_member(a, relation))
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(added > 0)
+    }
+    
+    /// Remove the links between two thoughts, only those labelled `relation` when
+    /// given, from both sides. Returns the relations that were removed.
+    pub async fn remove_relations(&self, a: &str, b: &str, relation: Option<&str>) -> Result<Vec<String>> {
+        let removed: Vec<String> = self
+            .relations(a)
+            .await?
+            .into_iter()
+            .filter(|(id, r)| id == b && relation.is_none_or(|wanted| wanted == r))
+            .map(|(_, r)| r)
+            .collect();
+        if removed.is_empty() {
+            return Ok(removed);
+        }
+        
+        let mut conn = self.conn().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for relation in &removed {
+            pipe.srem(self.make_relations_key(a), relation_member(b, relation)).ignore();
+            pipe.srem(self.make_relations_key(b), relation_member(a, relation)).ignore();
+        }
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(removed)
+    }
+    
+    /// Every link of thought `id` as `(other id, relation)`, sorted
+    pub async fn relations(&self, id: &str) -> Result<Vec<(String, String)>> {
+        let mut conn = self.conn().await?;
+        let members: Vec<String> = conn.smembers(self.make_relations_key(id)).await?;
+        let mut relations: Vec<(String, String)> = members
+            .iter()
+            .filter_map(|m| m.split_once(':'))
+            .map(|(other, relation)| (other.to_string(), relation.to_string()))
+            .collect();
+        relations.sort();
+        Ok(relations)
+    }
+    
+    /// Drop all links of a thought that is being deleted or moved, including the
+    /// other side of each
+    pub async fn drop_relations(&self, id: &str) -> Result<()> {
+        let relations = self.relations(id).await?;
+        let mut conn = self.conn().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (other, relation) in &relations {
+            pipe.srem(self.make_relations_key(other), relation_member(id, relation)).ignore();
+        }
+        pipe.del(self.make_relations_key(id)).ignore();
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+    
+    /// Content hash recorded for `path` by the last import from `source`
+    pub async fn import_hash(&self, source: &str, path: &str) -> Result<Option<String>> {
+        let mut conn = self.conn().await?;
+        let hash: Option<String> = conn.hget(self.make_import_key(source), path).await?;
+        Ok(hash)
+    }
+    
+    pub async fn set_import_hash(&self, source: &str, path: &str, hash: &str) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.hset::<_, _, _, ()>(self.make_import_key(source), path, hash).await?;
+        Ok(())
+    }
+    
+    /// Look up a cached embedding for `content`.
+    ///
+    /// Misses under the sha256 key fall back to the legacy md5 key (when enabled)
+    /// and copy any hit forward so the old entry isn't orphaned.
+    pub async fn get_embedding(&self, content: &str) -> Result<Option<Vec<f32>>> {
+        let mut conn = self.conn().await?;
+        let key = self.make_embedding_key(content);
+        
+        if let Some(json) = conn.get::<_, Option<String>>(&key).await? {
+            self.touch_embedding(&mut conn, &key).await;
+            return Ok(Some(serde_json::from_str(&json)?));
+        }
+        
+        // Legacy keys were only ever written for the default model
+        if !self.legacy_md5_keys || self.embedding_model.is_some() {
+            return Ok(None);
+        }
+        
+        let legacy_key = self.make_legacy_embedding_key(content);
+        match conn.get::<_, Option<String>>(&legacy_key).await? {
+            Some(json) => {
+                let embedding: Vec<f32> = serde_json::from_str(&json)?;
+                let _ = conn.set::<_, _, ()>(&key, &json).await;
+                self.touch_embedding(&mut conn, &key).await;
+                tracing::debug!("Migrated legacy embedding key {} -> {}", legacy_key, key);
+                Ok(Some(embedding))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    pub async fn set_embedding(&self, content: &str, embedding: &[f32]) -> Result<()> {
+        let mut conn = self.conn().await?;
+        let key = self.make_embedding_key(content);
+        conn.set::<_, _, ()>(&key, serde_json::to_string(embedding)?).await?;
+        self.touch_embedding(&mut conn, &key).await;
+        
+        if let Some(max) = self.embedding_cache_max_entries {
+            let entries: u64 = conn.zcard(EMBEDDING_LRU_KEY).await?;
+            if entries > max {
+                self.evict_embeddings(&mut conn, entries - max).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Record a use of embedding key `key` in the LRU set; best effort, since a
+    /// missed update only makes the entry look older than it is. Without
+    /// `EMBEDDING_CACHE_MAX_ENTRIES` nothing is ever evicted, so nothing is tracked.
+    async fn touch_embedding(&self, conn: &mut Connection, key: &str) {
+        if self.embedding_cache_max_entries.is_none() {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        if let Err(e) = conn.zadd::<_, _, _, ()>(EMBEDDING_LRU_KEY, key, now).await {
+            tracing::debug!("Failed to record use of embedding {}: {}", key, e);
+        }
+    }
+    
+    /// Drop the `count` least recently used cached embeddings
+    async fn evict_embeddings(&self, conn: &mut Connection, count: u64) -> Result<()> {
+        let evicted: Vec<(String, f64)> = conn.zpopmin(EMBEDDING_LRU_KEY, count as isize).await?;
+        if evicted.is_empty() {
+            return Ok(());
+        }
+        
+        let keys: Vec<&str> = evicted.iter().map(|(key, _)| key.as_str()).collect();
+        conn.del::<_, ()>(&keys).await?;
+        tracing::debug!("Evicted {} least recently used embeddings", keys.len());
+        Ok(())
+    }
+
 }
 
 #[async_trait]
 impl CacheLayer for RedisCache {
     async fn get(&self, key: &str) -> Result<Option<Memory>> {
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
         let full_key = self.make_thought_key(key);
         
-        // Try to get JSON data from Redis
-        let data: Option<String> = conn.get(&full_key).await?;
+        // Try to get the (possibly compressed) JSON data from Redis
+        let data: Option<Vec<u8>> = conn.get(&full_key).await?;
         
         match data {
-            Some(json) => {
-                let memory: Memory = serde_json::from_str(&json)?;
+            Some(bytes) => {
+                let memory = Self::decode(&bytes)?;
                 
                 // Update metadata access count and last_accessed
                 let metadata_key = self.make_metadata_key(key);
@@ -84,17 +791,32 @@ impl CacheLayer for RedisCache {
     }
     
     async fn set(&self, key: &str, memory: &Memory, ttl_seconds: Option<u64>) -> Result<()> {
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
         let thought_key = self.make_thought_key(key);
         let json = serde_json::to_string(memory)?;
+        let value = self.encode(&json)?;
         
         // Set the thought with optional TTL (though thoughts typically don't expire)
         if let Some(ttl) = ttl_seconds {
-            conn.set_ex::<_, _, ()>(&thought_key, &json, ttl).await?;
+            conn.set_ex::<_, _, ()>(&thought_key, &value, ttl).await?;
         } else {
-            conn.set::<_, _, ()>(&thought_key, &json).await?;
+            conn.set::<_, _, ()>(&thought_key, &value).await?;
         }
         
+        // Register the instance for cross-instance tooling
+        redis::pipe()
+            .sadd(INSTANCES_KEY, &self.prefix)
+            .hset(INSTANCE_LAST_WRITE_KEY, &self.prefix, chrono::Utc::now().to_rfc3339())
+            .query_async::<()>(&mut conn)
+            .await?;
+        
+        // Running byte totals for the compression ratio in stats
+        let _ = redis::pipe()
+            .hincr(self.make_compression_key(), "raw_bytes", json.len() as u64)
+            .hincr(self.make_compression_key(), "stored_bytes", value.len() as u64)
+            .query_async::<()>(&mut conn)
+            .await;
+        
         // Store metadata
         let metadata_key = self.make_metadata_key(key);
         let metadata = serde_json::json!({
@@ -103,6 +825,7 @@ impl CacheLayer for RedisCache {
             "importance": memory.metadata.importance,
             "category": memory.metadata.category,
             "tags": memory.metadata.tags,
+            "content_hash": memory.metadata.content_hash,
             "created_at": memory.created_at.to_rfc3339(),
             "last_accessed": memory.created_at.to_rfc3339(),
             "access_count": 0
@@ -124,89 +847,143 @@ impl CacheLayer for RedisCache {
         Ok(())
     }
     
-    async fn search_cached(&self, request: &SearchRequest) -> Result<Vec<Memory>> {
+    async fn search_cached(&self, request: &SearchRequest) -> Result<CachedSearch> {
         // Check if we have a cached search result first
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
         
-        // Create query hash for cache lookup
-        let query_hash = format!("{:x}", md5::compute(format!("{:?}", request)));
+        // Create query hash for cache lookup.
+        // The prefix is hashed in since each instance scans only its own thoughts.
+        let query_hash = text::sha256_hex(format!("{}|{}", self.prefix, request.canonical_cache_key()).as_bytes());
         let cache_key = self.make_cache_key(&query_hash);
         
+        // A zero TTL keeps this query out of the cache entirely
+        let ttl = request.cache_ttl.unwrap_or(self.search_cache_ttl_secs);
+        let use_cache = self.search_cache && ttl > 0;
+        
         // Try to get cached results
-        if let Ok(Some(cached)) = conn.get::<_, Option<String>>(&cache_key).await {
-            if let Ok(cached_result) = serde_json::from_str::<Vec<Memory>>(&cached) {
-                return Ok(cached_result);
+        if use_cache && !request.bypass_cache {
+            if let Ok(Some(cached)) = conn.get::<_, Option<String>>(&cache_key).await {
+                if let Ok(memories) = serde_json::from_str::<Vec<Memory>>(&cached) {
+                    return Ok(CachedSearch { memories, truncated: false });
+                }
             }
         }
         
-        // Otherwise, scan for thoughts
-        let pattern = format!("{}:Thoughts:*", self.prefix);
-        let mut cursor: u64 = 0;
+        let limit = request.limit.unwrap_or(20).min(self.max_results);
         let mut results = Vec::new();
         
-        loop {
-            let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-                .arg(cursor)
-                .arg("MATCH")
-                .arg(&pattern)
-                .arg("COUNT")
-                .arg(100)
-                .query_async(&mut conn)
-                .await?;
+        // Checked before each thought is loaded; past it, what was found so far is returned
+        let deadline = self.search_budget.map(|budget| Instant::now() + budget);
+        let out_of_time = || deadline.is_some_and(|d| Instant::now() >= d);
+        let mut truncated = false;
+        
+        if let Some(ref chain_id) = request.chain_filter {
+            // A chain filter walks the chain list in order; it is usually far smaller than the keyspace
+            let thought_ids: Vec<String> = conn.lrange(self.make_chain_key(chain_id), 0, -1).await?;
+            let mut seen = HashSet::new();
             
-            for key in keys {
-                // Extract thought_id from key
-                if let Some(thought_id) = key.strip_prefix(&format!("{}:Thoughts:", self.prefix)) {
-                    if let Some(memory) = self.get(thought_id).await? {
-                        // Apply filters
-                        if let Some(ref category) = request.category_filter {
-                            if memory.metadata.category.as_ref() != Some(category) {
-                                continue;
-                            }
-                        }
-                        
-                        if let Some(ref tags_filter) = request.tags_filter {
-                            let has_tag = tags_filter.iter().any(|tag| memory.metadata.tags.contains(tag));
-                            if !has_tag {
-                                continue;
-                            }
-                        }
-                        
-                        if let Some(ref instance_filter) = request.instance_filter {
-                            if !instance_filter.contains(&memory.instance_id) {
-                                continue;
-                            }
+            for thought_id in thought_ids {
+                if !seen.insert(thought_id.clone()) {
+                    continue;
+                }
+                if out_of_time() {
+                    truncated = true;
+                    break;
+                }
+                if let Some(memory) = self.get(&thought_id).await? {
+                    if Self::matches_filters(&memory, request) {
+                        results.push(memory);
+                        if results.len() >= limit {
+                            break;
                         }
-                        
+                    }
+                }
+            }
+        } else if let Some(tags) = request.tags_filter.as_ref().filter(|tags| !tags.is_empty()) {
+            // Tag filters resolve straight from the tag index sets instead of scanning:
+            // SINTER when every tag is required, otherwise SUNION narrowed by the filters below
+            let tag_keys: Vec<String> = tags.iter().map(|tag| self.make_tag_key(tag)).collect();
+            let command = if request.tag_min_match.unwrap_or(1) >= tags.len() { "SINTER" } else { "SUNION" };
+            let thought_ids: Vec<String> = redis::cmd(command).arg(&tag_keys).query_async(&mut conn).await?;
+            
+            for thought_id in thought_ids {
+                if out_of_time() {
+                    truncated = true;
+                    break;
+                }
+                // Index entries can outlive thoughts that expired via TTL
+                if let Some(memory) = self.get(&thought_id).await? {
+                    if Self::matches_filters(&memory, request) {
                         results.push(memory);
-                        
-                        if results.len() >= request.limit.unwrap_or(20) {
+                        if results.len() >= limit {
                             break;
                         }
                     }
                 }
             }
+        } else {
+            // Otherwise, scan for thoughts
+            let pattern = self.instance_key(&self.keys.thoughts, "*");
+            let mut cursor: u64 = 0;
             
-            cursor = new_cursor;
-            if cursor == 0 || results.len() >= request.limit.unwrap_or(20) {
-                break;
+            loop {
+                // SCAN rounds over a large keyspace often match nothing, so they count too
+                if out_of_time() {
+                    truncated = true;
+                    break;
+                }
+                let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(100)
+                    .query_async(&mut conn)
+                    .await?;
+                
+                for key in keys {
+                    if out_of_time() {
+                        truncated = true;
+                        break;
+                    }
+                    // Extract thought_id from key
+                    if let Some(thought_id) = key.strip_prefix(&self.instance_key(&self.keys.thoughts, "")) {
+                        if let Some(memory) = self.get(thought_id).await? {
+                            if Self::matches_filters(&memory, request) {
+                                results.push(memory);
+                                
+                                if results.len() >= limit {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                
+                cursor = new_cursor;
+                if cursor == 0 || results.len() >= limit || truncated {
+                    break;
+                }
             }
         }
         
-        // Cache the results with TTL
-        if !results.is_empty() {
+        // Cache the results with TTL; a cut-short search isn't worth repeating
+        if use_cache && !truncated && !results.is_empty() {
             let _ = conn.set_ex::<_, _, ()>(
                 &cache_key,
                 serde_json::to_string(&results)?,
-                3600 // 1 hour TTL
+                ttl
             ).await;
         }
         
-        Ok(results)
+        if truncated {
+            tracing::warn!("Keyword search of {} ran out of its time budget after {} matches", self.prefix, results.len());
+        }
+        Ok(CachedSearch { memories: results, truncated })
     }
     
     async fn invalidate(&self, key: &str) -> Result<()> {
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
         
         // Get the memory first to clean up related data
         if let Some(memory) = self.get(key).await? {
@@ -234,10 +1011,10 @@ impl CacheLayer for RedisCache {
     }
     
     async fn get_stats(&self) -> Result<CacheStats> {
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
         
         // Count thoughts using SCAN to avoid blocking
-        let pattern = format!("{}:Thoughts:*", self.prefix);
+        let pattern = self.instance_key(&self.keys.thoughts, "*");
         let mut cursor: u64 = 0;
         let mut total_keys = 0u64;
         
@@ -275,12 +1052,88 @@ impl CacheLayer for RedisCache {
             }
         }
         
+        let (raw_bytes, stored_bytes): (Option<u64>, Option<u64>) = redis::cmd("HMGET")
+            .arg(self.make_compression_key())
+            .arg("raw_bytes")
+            .arg("stored_bytes")
+            .query_async(&mut conn)
+            .await?;
+        let compression_ratio = match (raw_bytes, stored_bytes) {
+            (Some(raw), Some(stored)) if stored > 0 => raw as f32 / stored as f32,
+            _ => 1.0,
+        };
+        let embedding_cache_entries: u64 = conn.zcard(EMBEDDING_LRU_KEY).await?;
+        
         Ok(CacheStats {
             total_keys,
             memory_usage_bytes: memory_usage,
+            compression_ratio,
+            embedding_cache_entries,
+            embedding_cache_max_entries: self.embedding_cache_max_entries,
             hit_rate: 0.0, // TODO: Implement hit rate tracking
             miss_rate: 0.0, // TODO: Implement miss rate tracking
             avg_retrieval_time_ms: 0.0, // TODO: Implement timing
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_redis::{Config as PoolConfig, Runtime};
+    
+    /// A cache over a pool that never connects; key builders don't touch Redis
+    fn cache() -> RedisCache {
+        let pool = PoolConfig::from_url("redis://127.0.0.1:6379/0")
+            .create_pool(Some(Runtime::Tokio1))
+            .unwrap();
+        let config = RedisConfig {
+            host: "127.0.0.1".to_string(),
+            port: 6379,
+            password: None,
+            pool_size: 1,
+            legacy_md5_keys: true,
+            retry_attempts: 0,
+            compress: false,
+            search_cache: true,
+            search_cache_ttl_secs: 300,
+            idempotency_ttl_secs: 86_400,
+            embedding_cache_max_entries: None,
+            search_budget_ms: 0,
+            key_scheme: KeyScheme::default(),
+        };
+        RedisCache::new(Arc::new(pool), "CC", &config)
+    }
+    
+    #[test]
+    fn embedding_key_is_sha256_of_normalized_content() {
+        assert_eq!(
+            cache().make_embedding_key("hello"),
+            "um:embedding:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        // Composed and decomposed "é" are the same text
+        assert_eq!(cache().make_embedding_key("caf\u{e9}"), cache().make_embedding_key("cafe\u{301}"));
+    }
+    
+    #[test]
+    fn legacy_embedding_key_is_md5() {
+        assert_eq!(
+            cache().make_legacy_embedding_key("hello"),
+            "um:embedding:5d41402abc4b2a76b9719d911017c592"
+        );
+    }
+    
+    #[test]
+    fn embedding_key_is_namespaced_by_non_default_model() {
+        let hash = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let large = cache().with_embedding_model("text-embedding-3-large");
+        assert_eq!(large.make_embedding_key("hello"), format!("um:embedding:text-embedding-3-large:{}", hash));
+        let default = cache().with_embedding_model(DEFAULT_EMBEDDING_MODEL);
+        assert_eq!(default.make_embedding_key("hello"), format!("um:embedding:{}", hash));
+    }
+    
+    #[test]
+    fn search_cache_key_format() {
+        assert_eq!(cache().make_cache_key("abc123"), "um:cache:abc123");
+    }
+}
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
 use crate::error::{Result, UnifiedRagError};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,10 +8,36 @@ pub struct Config {
     pub redis: RedisConfig,
     pub qdrant: QdrantConfig,
     pub openai: OpenAIConfig,
+    pub rerank: RerankConfig,
+    pub warmup: WarmupConfig,
+    pub rate_limit: RateLimitConfig,
     pub instance_id: String,
+    /// Where the authoritative copy of each memory lives
+    pub storage_mode: StorageMode,
+    /// Address for the `/healthz` and `/readyz` HTTP endpoints; off when unset
+    pub health_addr: Option<String>,
     pub cache_ttl_seconds: u64,
+    /// Most memories one Redis keyword search returns, whatever the requested limit
     pub max_results: usize,
     pub similarity_threshold: f32,
+    /// Minimum cosine similarity score at which a stored memory counts as a duplicate
+    pub dedup_threshold: f32,
+    /// Minimum score at which `rag_store` with `auto_link` adopts the nearest memory as parent
+    pub auto_link_threshold: f32,
+    /// Let `auto_link` replace a `parent_id` the caller passed
+    pub auto_link_overwrite_parent: bool,
+    /// Candidates fetched per requested search result, so post-filtering can still fill the page
+    pub search_candidate_multiplier: usize,
+    /// Largest `offset + limit` a search may ask for
+    pub max_search_window: usize,
+    /// Scroll pages `rag_migrate_model` loads, embeds and indexes at once
+    pub migration_concurrency: usize,
+    /// Most tags one memory may carry; unlimited when unset
+    pub max_tags: Option<usize>,
+    /// Drop tags past `max_tags` instead of rejecting the write
+    pub truncate_excess_tags: bool,
+    /// Largest `rag_search` response in bytes of JSON; results past it are left for the next page
+    pub max_response_bytes: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,51 +46,466 @@ pub struct RedisConfig {
     pub port: u16,
     pub password: Option<String>,
     pub pool_size: usize,
+    /// Fall back to the pre-sha256 md5 embedding keys on a cache miss
+    pub legacy_md5_keys: bool,
+    /// Retries for transient connection errors when checking out a pooled connection
+    pub retry_attempts: u32,
+    /// Gzip thought values on write; reads accept both compressed and plain values
+    pub compress: bool,
+    /// Read and write the `um:cache:*` search result cache
+    pub search_cache: bool,
+    /// Default lifetime of a cached search result; 0 disables caching unless a query sets its own TTL
+    pub search_cache_ttl_secs: u64,
+    /// How long a `rag_store` idempotency key remembers its result
+    pub idempotency_ttl_secs: u64,
+    /// Cached embeddings kept before the least recently used are evicted; unbounded when unset
+    pub embedding_cache_max_entries: Option<u64>,
+    /// Wall-clock budget for one instance's keyword search; unbounded when 0
+    pub search_budget_ms: u64,
+    /// Layout of the per-instance keys
+    pub key_scheme: KeyScheme,
 }
 
+/// Per-instance Redis key layout: `<instance><separator><segment><separator><id>`.
+///
+/// Defaults match the historical `CC:Thoughts:<id>` keys. The shared `um:*` keys
+/// and the `imports`/`idempotency`/`compression`/`embedding_queue`/`relations` segments only follow `separator`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyScheme {
+    pub separator: String,
+    pub thoughts: String,
+    pub thought_meta: String,
+    pub tags: String,
+    pub chains: String,
+}
+
+impl Default for KeyScheme {
+    fn default() -> Self {
+        Self {
+            separator: ":".to_string(),
+            thoughts: "Thoughts".to_string(),
+            thought_meta: "thought_meta".to_string(),
+            tags: "tags".to_string(),
+            chains: "chains".to_string(),
+        }
+    }
+}
+
+impl KeyScheme {
+    /// Reject schemes whose keys can't be told apart or matched with SCAN: every
+    /// part must be non-empty and free of glob characters, segments must be
+    /// distinct and must not contain the separator
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| Err(UnifiedRagError::Configuration(format!("Invalid Redis key scheme: {}", reason)));
+        
+        if self.separator.is_empty() || has_glob(&self.separator) {
+            return invalid(format!("separator {:?} must be non-empty and contain no glob characters", self.separator));
+        }
+        let mut segments: Vec<&str> = vec![&self.thoughts, &self.thought_meta, &self.tags, &self.chains];
+        segments.extend(RESERVED_KEY_SEGMENTS);
+        for (i, segment) in segments.iter().enumerate() {
+            if segment.is_empty() || has_glob(segment) || segment.contains(self.separator.as_str()) {
+                return invalid(format!(
+                    "segment {:?} must be non-empty, contain no glob characters and not contain the separator {:?}",
+                    segment, self.separator
+                ));
+            }
+            if segments[..i].contains(segment) {
+                return invalid(format!("segment {:?} is used twice", segment));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Why `instance_id` can't serve as a key prefix under this scheme, if it can't:
+    /// an empty id, one containing the separator, which can shadow another
+    /// instance's keys, or one with glob characters, which would make the
+    /// instance's SCAN patterns match other instances' keys
+    pub fn instance_id_problem(&self, instance_id: &str) -> Option<String> {
+        if instance_id.trim().is_empty() {
+            return Some("instance id must not be empty".to_string());
+        }
+        if instance_id.contains(self.separator.as_str()) {
+            return Some(format!(
+                "instance id {:?} contains the Redis key separator {:?}; its keys may be confused with another instance's",
+                instance_id, self.separator
+            ));
+        }
+        has_glob(instance_id).then(|| format!(
+            "instance id {:?} contains a glob character (*, ?, [, ] or \\); its key patterns would match other instances' keys",
+            instance_id
+        ))
+    }
+}
+
+/// Whether `part` has a character SCAN MATCH treats as a pattern
+fn has_glob(part: &str) -> bool {
+    part.contains(['*', '?', '[', ']', '\\'])
+}
+
+/// Embedding model used unless `EMBEDDING_MODEL` names another
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Fixed per-instance key segments that configurable ones must not collide with
+pub const RESERVED_KEY_SEGMENTS: [&str; 5] = ["imports", "idempotency", "compression", "embedding_queue", "relations"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QdrantConfig {
     pub host: String,
     pub port: u16,
+    pub protocol: String,
     pub collection_name: String,
+    /// Per-request timeout
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    /// Concurrent gRPC connections; 0 or 1 disables pooling
+    pub pool_size: usize,
+    /// Health-check attempts at startup before giving up
+    pub startup_retries: u32,
+    /// HNSW index settings for a newly created collection
+    pub hnsw: HnswConfig,
+    /// Create the collection at startup when it is missing, instead of failing
+    pub auto_create: bool,
+    /// Most points one bulk upsert sends
+    pub upsert_batch_size: usize,
+    /// Create the full-text payload index on `content` at startup, for `text_match`
+    pub text_index: bool,
+    /// Optional `Memory` fields written to point payloads, on top of the ones
+    /// deserialization and filters need
+    pub payload_fields: Vec<String>,
+}
+
+/// HNSW build parameters applied when the collection is created; unset fields keep
+/// Qdrant's defaults. Existing collections are left as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Edges per node; higher improves recall at the cost of memory
+    pub m: Option<u64>,
+    /// Neighbors considered while building; higher improves index quality at the cost of build time
+    pub ef_construct: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIConfig {
-    pub api_key: String,
+    /// Unset runs the server without embeddings: only cache operations work
+    pub api_key: Option<String>,
     pub model: String,
+    /// Fallback vector size when the startup probe can't reach the embedding API
+    pub dimension: u64,
+    /// Embed locally with hashed bag-of-words vectors of `dimension` instead of calling
+    /// the API; not semantic, for tests and offline development only
+    pub hash_embeddings: bool,
+    /// Inputs per embeddings request when embedding a batch
+    pub batch_size: usize,
+    /// Sub-batch requests in flight at once
+    pub batch_concurrency: usize,
+    /// Largest content (in tokens of the model's encoding) sent to the embedding model in one piece
+    pub max_content_tokens: usize,
+    /// Split over-long `rag_store` content into chained chunks instead of rejecting it
+    pub chunk_long_content: bool,
+    /// Content shorter than this (characters, after preprocessing) is stored unembedded; off when unset
+    pub min_embed_chars: Option<usize>,
+    /// Per-request timeout for the OpenAI HTTP client
+    pub timeout_secs: u64,
+    /// Store to Redis and queue the embedding for later when it can't be generated
+    pub defer_failed_embeddings: bool,
+    /// Seconds between background retries of deferred embeddings
+    pub embedding_retry_interval_secs: u64,
+    /// Strip Markdown syntax from content before embedding it; stored content is unchanged
+    pub strip_markdown: bool,
+    /// Strip HTML tags and decode entities before embedding; stored content is unchanged
+    pub strip_html: bool,
+    /// Directory `rag_count_tokens` reads tiktoken encoding files from and saves downloaded ones to
+    pub tiktoken_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankConfig {
+    /// Cohere/Jina-style rerank endpoint; reranking is unavailable when unset
+    pub url: Option<String>,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    /// Candidates fetched per requested result when reranking
+    pub candidate_multiplier: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    /// Embed `queries` at startup so their first searches hit the embedding cache
+    pub enabled: bool,
+    pub queries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Requests per second allowed for each named tool
+    pub per_tool: HashMap<String, f64>,
+    /// Requests per second for tools not listed in `per_tool`; unlimited when unset
+    pub default: Option<f64>,
+}
+
+/// Which backend holds full memories; set with `STORAGE_MODE`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    /// Full memories in both Redis and Qdrant
+    #[default]
+    Dual,
+    /// Full memories in Redis; Qdrant keeps vectors and filter metadata only
+    RedisPrimary,
+    /// Full memories in Qdrant; Redis keeps only the embedding and search caches
+    QdrantPrimary,
+}
+
+impl StorageMode {
+    /// Whether memories are written to Redis
+    pub fn stores_in_redis(self) -> bool {
+        self != Self::QdrantPrimary
+    }
+    
+    /// Whether Qdrant payloads carry content, or only what filters need
+    pub fn full_qdrant_payload(self) -> bool {
+        self != Self::RedisPrimary
+    }
+}
+
+impl FromStr for StorageMode {
+    type Err = String;
+    
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dual" => Ok(Self::Dual),
+            "redis_primary" => Ok(Self::RedisPrimary),
+            "qdrant_primary" => Ok(Self::QdrantPrimary),
+            other => Err(format!("unknown storage mode: {}", other)),
+        }
+    }
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
-        Ok(Self {
+        let config = Self {
             redis: RedisConfig {
                 host: std::env::var("REDIS_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-                port: std::env::var("REDIS_PORT")
-                    .unwrap_or_else(|_| "6379".to_string())
-                    .parse()
-                    .unwrap_or(6379),
+                port: env_or("REDIS_PORT", 6379),
                 password: std::env::var("REDIS_PASSWORD").ok(),
                 pool_size: 10,
+                legacy_md5_keys: env_flag("REDIS_LEGACY_MD5_KEYS", true),
+                retry_attempts: env_or("REDIS_RETRY_ATTEMPTS", 3),
+                compress: env_flag("REDIS_COMPRESS", false),
+                search_cache: !env_flag("DISABLE_SEARCH_CACHE", false),
+                search_cache_ttl_secs: env_or("SEARCH_CACHE_TTL", 3600),
+                idempotency_ttl_secs: env_or("IDEMPOTENCY_TTL_SECS", 86400),
+                embedding_cache_max_entries: std::env::var("EMBEDDING_CACHE_MAX_ENTRIES")
+                    .ok()
+                    .and_then(|v| v.trim().parse().ok())
+                    .filter(|&max: &u64| max > 0),
+                search_budget_ms: env_or("CACHE_SEARCH_BUDGET_MS", 500),
+                key_scheme: key_scheme_from_env()?,
             },
             qdrant: QdrantConfig {
                 host: std::env::var("QDRANT_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-                port: std::env::var("QDRANT_PORT")
-                    .unwrap_or_else(|_| "6333".to_string())
-                    .parse()
-                    .unwrap_or(6333),
+                // gRPC port; the Rust client has trouble negotiating HTTP/2 on 6333
+                port: env_or("QDRANT_PORT", 6334),
+                protocol: std::env::var("QDRANT_PROTOCOL").unwrap_or_else(|_| "http".to_string()),
                 collection_name: std::env::var("QDRANT_COLLECTION")
                     .unwrap_or_else(|_| "unified_rag".to_string()),
+                timeout_secs: env_or("QDRANT_TIMEOUT_SECS", 30),
+                connect_timeout_secs: env_or("QDRANT_CONNECT_TIMEOUT_SECS", 5),
+                pool_size: env_or("QDRANT_POOL_SIZE", 3),
+                startup_retries: env_or("QDRANT_STARTUP_RETRIES", 10),
+                hnsw: HnswConfig {
+                    m: std::env::var("QDRANT_HNSW_M").ok().and_then(|v| v.trim().parse().ok()),
+                    ef_construct: std::env::var("QDRANT_HNSW_EF_CONSTRUCT").ok().and_then(|v| v.trim().parse().ok()),
+                },
+                auto_create: env_flag("QDRANT_AUTO_CREATE", true),
+                upsert_batch_size: env_or("QDRANT_UPSERT_BATCH_SIZE", 256).max(1),
+                text_index: env_flag("QDRANT_TEXT_INDEX", true),
+                payload_fields: payload_fields_from_env()?,
             },
             openai: OpenAIConfig {
-                api_key: std::env::var("OPENAI_API_KEY")
-                    .map_err(|_| UnifiedRagError::Configuration("OPENAI_API_KEY not set".to_string()))?,
-                model: "text-embedding-3-small".to_string(),
+                api_key: std::env::var("OPENAI_API_KEY").ok().filter(|key| !key.trim().is_empty()),
+                model: std::env::var("EMBEDDING_MODEL")
+                    .ok()
+                    .filter(|m| !m.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string()),
+                dimension: env_or("EMBEDDING_DIMENSION", 1536),
+                hash_embeddings: env_flag("HASH_EMBEDDINGS", false),
+                batch_size: env_or("EMBEDDING_BATCH_SIZE", 100).max(1),
+                batch_concurrency: env_or("EMBEDDING_BATCH_CONCURRENCY", 1).max(1),
+                max_content_tokens: env_or("MAX_CONTENT_TOKENS", 8191).max(1),
+                chunk_long_content: env_flag("CHUNK_LONG_CONTENT", false),
+                min_embed_chars: Some(env_or("MIN_EMBED_CHARS", 0)).filter(|min: &usize| *min > 0),
+                timeout_secs: env_or("OPENAI_TIMEOUT_SECS", 30).max(1),
+                defer_failed_embeddings: env_flag("DEFER_FAILED_EMBEDDINGS", false),
+                embedding_retry_interval_secs: env_or("EMBEDDING_RETRY_INTERVAL_SECS", 60).max(1),
+                strip_markdown: env_flag("STRIP_MARKDOWN", false),
+                strip_html: env_flag("STRIP_HTML", false),
+                tiktoken_dir: std::env::var("TIKTOKEN_DIR").ok().filter(|d| !d.trim().is_empty()),
+            },
+            rerank: RerankConfig {
+                url: std::env::var("RERANK_URL").ok(),
+                api_key: std::env::var("RERANK_API_KEY").ok(),
+                model: std::env::var("RERANK_MODEL").ok(),
+                candidate_multiplier: env_or("RERANK_CANDIDATES", 3).max(1),
+            },
+            warmup: WarmupConfig {
+                enabled: env_flag("WARM_CACHE", false),
+                queries: std::env::var("WARM_QUERIES")
+                    .map(|v| {
+                        v.split(';')
+                            .map(str::trim)
+                            .filter(|q| !q.is_empty())
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            rate_limit: RateLimitConfig {
+                per_tool: std::env::var("RATE_LIMITS")
+                    .map(|v| parse_rate_limits(&v))
+                    .unwrap_or_default(),
+                default: std::env::var("RATE_LIMIT_DEFAULT")
+                    .ok()
+                    .and_then(|v| v.trim().parse().ok())
+                    .filter(|rate: &f64| *rate > 0.0),
             },
             instance_id: std::env::var("INSTANCE_ID").unwrap_or_else(|_| "CC".to_string()),
+            storage_mode: match std::env::var("STORAGE_MODE") {
+                Ok(mode) => mode.parse().map_err(UnifiedRagError::Configuration)?,
+                Err(_) => StorageMode::Dual,
+            },
+            health_addr: std::env::var("HEALTH_ADDR").ok().filter(|a| !a.trim().is_empty()),
             cache_ttl_seconds: 3600, // 1 hour default
-            max_results: 20,
-            similarity_threshold: 0.7,
-        })
+            max_results: env_or("MAX_RESULTS", 20).max(1),
+            similarity_threshold: env_or("SIMILARITY_THRESHOLD", 0.7f32).clamp(0.0, 1.0),
+            dedup_threshold: env_or("DEDUP_THRESHOLD", 0.95f32).clamp(-1.0, 1.0),
+            auto_link_threshold: env_or("AUTO_LINK_THRESHOLD", 0.85f32).clamp(-1.0, 1.0),
+            auto_link_overwrite_parent: env_flag("AUTO_LINK_OVERWRITE_PARENT", false),
+            search_candidate_multiplier: env_or("SEARCH_CANDIDATE_MULTIPLIER", 1).max(1),
+            max_search_window: env_or("MAX_SEARCH_WINDOW", 1000).max(1),
+            migration_concurrency: env_or("MIGRATION_CONCURRENCY", 4).max(1),
+            max_tags: Some(env_or("MAX_TAGS", 0)).filter(|max: &usize| *max > 0),
+            truncate_excess_tags: env_flag("TRUNCATE_EXCESS_TAGS", false),
+            max_response_bytes: Some(env_or("MAX_RESPONSE_BYTES", 1_000_000)).filter(|max: &usize| *max > 0),
+        };
+        
+        if let Some(problem) = config.redis.key_scheme.instance_id_problem(&config.instance_id) {
+            tracing::warn!("INSTANCE_ID: {}", problem);
+        }
+        Ok(config)
+    }
+
+    pub fn redis_url(&self) -> String {
+        match &self.redis.password {
+            Some(password) => format!("redis://:{}@{}:{}/0", password, self.redis.host, self.redis.port),
+            None => format!("redis://{}:{}/0", self.redis.host, self.redis.port),
+        }
+    }
+
+    pub fn qdrant_url(&self) -> String {
+        format!("{}://{}:{}", self.qdrant.protocol, self.qdrant.host, self.qdrant.port)
     }
-}
\ No newline at end of file
+}
+
+/// Parse an env var, falling back to `default` when unset or malformed
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read `REDIS_KEY_*` overrides on top of the default scheme and validate the result
+fn key_scheme_from_env() -> Result<KeyScheme> {
+    let defaults = KeyScheme::default();
+    let var = |key: &str, default: String| std::env::var(key).unwrap_or(default);
+    let scheme = KeyScheme {
+        separator: var("REDIS_KEY_SEPARATOR", defaults.separator),
+        thoughts: var("REDIS_KEY_THOUGHTS", defaults.thoughts),
+        thought_meta: var("REDIS_KEY_THOUGHT_META", defaults.thought_meta),
+        tags: var("REDIS_KEY_TAGS", defaults.tags),
+        chains: var("REDIS_KEY_CHAINS", defaults.chains),
+    };
+    scheme.validate()?;
+    Ok(scheme)
+}
+
+/// `Memory` fields `QDRANT_PAYLOAD_FIELDS` may add to point payloads. `embedding`
+/// repeats the content vector Qdrant already stores, so it is left out by default.
+pub const OPTIONAL_PAYLOAD_FIELDS: [&str; 4] = ["summary", "access_count", "relevance_score", "embedding"];
+
+/// Read `QDRANT_PAYLOAD_FIELDS`, a comma-separated subset of `OPTIONAL_PAYLOAD_FIELDS`;
+/// unset keeps every optional field but `embedding`, and `none` keeps none
+fn payload_fields_from_env() -> Result<Vec<String>> {
+    let Ok(value) = std::env::var("QDRANT_PAYLOAD_FIELDS") else {
+        return Ok(["summary", "access_count", "relevance_score"].map(String::from).to_vec());
+    };
+    if value.trim().eq_ignore_ascii_case("none") {
+        return Ok(Vec::new());
+    }
+    
+    let mut fields = Vec::new();
+    for field in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !OPTIONAL_PAYLOAD_FIELDS.contains(&field) {
+            return Err(UnifiedRagError::Configuration(format!(
+                "Unknown QDRANT_PAYLOAD_FIELDS entry {:?}; expected some of {}",
+                field, OPTIONAL_PAYLOAD_FIELDS.join(", ")
+            )));
+        }
+        if !fields.iter().any(|f| f == field) {
+            fields.push(field.to_string());
+        }
+    }
+    Ok(fields)
+}
+
+/// Parse `tool=rate` pairs separated by commas, e.g. `rag_search=5,rag_store=2.5`.
+///
+/// Malformed or non-positive entries are skipped with a warning.
+fn parse_rate_limits(value: &str) -> HashMap<String, f64> {
+    let mut limits = HashMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.split_once('=').map(|(tool, rate)| (tool.trim(), rate.trim().parse::<f64>())) {
+            Some((tool, Ok(rate))) if !tool.is_empty() && rate > 0.0 => {
+                limits.insert(tool.to_string(), rate);
+            }
+            _ => tracing::warn!("Ignoring malformed RATE_LIMITS entry: {:?}", entry),
+        }
+    }
+    limits
+}
+
+/// Read a boolean env var; accepts 1/0, true/false, yes/no, on/off
+fn env_flag(key: &str, default: bool) -> bool {
+    match std::env::var(key) {
+        Ok(v) => match v.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => default,
+        },
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn instance_id_with_glob_characters_is_rejected() {
+        let keys = KeyScheme::default();
+        for id in ["a*", "x[ab]", "who?", "back\\slash"] {
+            assert!(keys.instance_id_problem(id).is_some(), "{:?} was accepted", id);
+        }
+    }
+    
+    #[test]
+    fn instance_id_with_separator_or_blank_is_rejected() {
+        let keys = KeyScheme::default();
+        assert!(keys.instance_id_problem("team:CC").is_some());
+        assert!(keys.instance_id_problem("  ").is_some());
+        assert!(keys.instance_id_problem("CC").is_none());
+        assert!(keys.instance_id_problem("team-b_2").is_none());
+    }
+}
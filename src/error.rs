@@ -14,23 +14,93 @@ pub enum UnifiedRagError {
     #[error("OpenAI error: {0}")]
     OpenAI(#[from] async_openai::error::OpenAIError),
     
+    #[error("OpenAI rate limit: {0}")]
+    OpenAIRateLimit(String),
+    
+    #[error("OpenAI authentication failed: {0}")]
+    OpenAIAuth(String),
+    
+    #[error("OpenAI server error: {0}")]
+    OpenAIServer(String),
+    
+    #[error("Embeddings unavailable: {0}")]
+    EmbeddingsUnavailable(String),
+    
     #[error("Invalid configuration: {0}")]
     Configuration(String),
     
-    #[error("Cache miss for key: {0}")]
-    CacheMiss(String),
-    
     #[error("Search failed: {0}")]
     SearchError(String),
     
+    #[error("Compression error: {0}")]
+    Compression(String),
+    
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
     #[error("Tool execution error: {0}")]
     ToolError(String),
-    
-    #[error("Invalid session: {0}")]
-    InvalidSession(String),
 }
 
-pub type Result<T> = std::result::Result<T, UnifiedRagError>;
\ No newline at end of file
+/// Code for tool arguments that fail validation
+pub const INVALID_INPUT: &str = "INVALID_INPUT";
+/// Code for calls rejected by the per-tool rate limiter
+pub const RATE_LIMITED: &str = "RATE_LIMITED";
+/// Code for failures that aren't tied to a backend, such as encoding a response
+pub const INTERNAL_ERROR: &str = "INTERNAL_ERROR";
+
+impl UnifiedRagError {
+    /// Stable machine-readable code, sent as `data.code` in tool errors so clients
+    /// can tell retryable backend outages from errors to surface to the user
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Redis(_) | Self::RedisPool(_) => "REDIS_UNAVAILABLE",
+            Self::Qdrant(_) => "QDRANT_UNAVAILABLE",
+            Self::OpenAIRateLimit(_) => "OPENAI_RATE_LIMIT",
+            Self::OpenAIAuth(_) => "OPENAI_AUTH",
+            Self::OpenAIServer(_) => "OPENAI_UNAVAILABLE",
+            Self::EmbeddingsUnavailable(_) => "EMBEDDINGS_UNAVAILABLE",
+            Self::OpenAI(async_openai::error::OpenAIError::InvalidArgument(_)) => INVALID_INPUT,
+            Self::OpenAI(_) => "OPENAI_ERROR",
+            Self::Configuration(_) => "CONFIGURATION_ERROR",
+            Self::SearchError(_) => "SEARCH_FAILED",
+            Self::Compression(_) => "CORRUPT_DATA",
+            Self::Serialization(_) => "SERIALIZATION_ERROR",
+            Self::ToolError(_) => "TOOL_ERROR",
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, UnifiedRagError>;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::OpenAIError;
+    
+    fn serde_error() -> serde_json::Error {
+        serde_json::from_str::<u8>("not json").unwrap_err()
+    }
+    
+    #[test]
+    fn each_variant_has_its_code() {
+        let cases = [
+            (UnifiedRagError::Redis(redis::RedisError::from((redis::ErrorKind::IoError, "down"))), "REDIS_UNAVAILABLE"),
+            (UnifiedRagError::RedisPool(deadpool_redis::PoolError::Timeout(deadpool_redis::TimeoutType::Wait)), "REDIS_UNAVAILABLE"),
+            (UnifiedRagError::Qdrant("down".to_string()), "QDRANT_UNAVAILABLE"),
+            (UnifiedRagError::OpenAI(OpenAIError::StreamError("closed".to_string())), "OPENAI_ERROR"),
+            (UnifiedRagError::OpenAI(OpenAIError::InvalidArgument("bad".to_string())), INVALID_INPUT),
+            (UnifiedRagError::OpenAIRateLimit("slow down".to_string()), "OPENAI_RATE_LIMIT"),
+            (UnifiedRagError::OpenAIAuth("bad key".to_string()), "OPENAI_AUTH"),
+            (UnifiedRagError::OpenAIServer("503".to_string()), "OPENAI_UNAVAILABLE"),
+            (UnifiedRagError::EmbeddingsUnavailable("no key".to_string()), "EMBEDDINGS_UNAVAILABLE"),
+            (UnifiedRagError::Configuration("bad".to_string()), "CONFIGURATION_ERROR"),
+            (UnifiedRagError::SearchError("failed".to_string()), "SEARCH_FAILED"),
+            (UnifiedRagError::Compression("bad gzip".to_string()), "CORRUPT_DATA"),
+            (UnifiedRagError::Serialization(serde_error()), "SERIALIZATION_ERROR"),
+            (UnifiedRagError::ToolError("failed".to_string()), "TOOL_ERROR"),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.code(), code, "{:?}", error);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::models::Memory;
+
+const HEADER: [&str; 7] = [
+    "id", "instance_id", "category", "tags", "importance", "created_at", "access_count",
+];
+
+/// Render memory metadata as RFC 4180 CSV; content and embeddings are left out.
+/// Tags are joined with `,` inside a single (quoted) field.
+pub fn metadata_csv(memories: &[Memory]) -> String {
+    let mut out = String::new();
+    write_row(&mut out, HEADER.iter().map(|h| h.to_string()));
+    
+    for memory in memories {
+        write_row(&mut out, [
+            memory.id.to_string(),
+            memory.instance_id.clone(),
+            memory.metadata.category.clone().unwrap_or_default(),
+            memory.metadata.tags.join(","),
+            memory.metadata.importance.to_string(),
+            memory.created_at.to_rfc3339(),
+            memory.access_count.to_string(),
+        ]);
+    }
+    
+    out
+}
+
+fn write_row(out: &mut String, fields: impl IntoIterator<Item = String>) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&escape(&field));
+    }
+    out.push_str("\r\n");
+}
+
+/// Quote a field when it contains a delimiter, quote or line break, doubling embedded quotes
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use crate::models::Memory;
+
+/// Longest memory heading before it is cut with `…`
+const TITLE_CHARS: usize = 80;
+
+/// Render a chain as one Markdown document, in chain order.
+///
+/// The document frontmatter describes the chain; each entry becomes a numbered
+/// `##` heading (the memory's first line) followed by its content, with the
+/// memory's metadata in a footnote. Entries whose memory couldn't be loaded get
+/// a placeholder section so the numbering still matches the chain.
+pub fn chain_markdown(chain_id: &str, entries: &[(String, Option<Memory>)], exported_at: DateTime<Utc>) -> String {
+    let missing = entries.iter().filter(|(_, memory)| memory.is_none()).count();
+    
+    let mut out = String::new();
+    out.push_str("---\n");
+    out.push_str(&format!("chain_id: {}\n", yaml_string(chain_id)));
+    out.push_str(&format!("exported_at: {}\n", exported_at.to_rfc3339()));
+    out.push_str(&format!("memories: {}\n", entries.len() - missing));
+    out.push_str(&format!("missing: {}\n", missing));
+    out.push_str("---\n\n");
+    out.push_str(&format!("# Chain {}\n", chain_id));
+    
+    let mut footnotes = Vec::new();
+    for (i, (id, memory)) in entries.iter().enumerate() {
+        let n = i + 1;
+        match memory {
+            Some(memory) => {
+                out.push_str(&format!("\n## {}. {}[^{}]\n\n", n, title(&memory.content), n));
+                out.push_str(memory.content.trim());
+                out.push('\n');
+                footnotes.push(format!("[^{}]: {}", n, metadata_line(memory)));
+            }
+            None => {
+                out.push_str(&format!("\n## {}. (missing memory)\n\n", n));
+                out.push_str(&format!("> Memory `{}` is linked in this chain but could not be found.\n", id));
+            }
+        }
+    }
+    
+    if !footnotes.is_empty() {
+        out.push('\n');
+        for footnote in footnotes {
+            out.push_str(&footnote);
+            out.push('\n');
+        }
+    }
+    
+    out
+}
+
+/// First non-empty line of the content, without leading heading markers
+fn title(content: &str) -> String {
+    let line = content
+        .lines()
+        .map(|l| l.trim().trim_start_matches('#').trim())
+        .find(|l| !l.is_empty())
+        .unwrap_or("(empty)");
+    
+    if line.chars().count() > TITLE_CHARS {
+        let cut: String = line.chars().take(TITLE_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+fn metadata_line(memory: &Memory) -> String {
+    let mut parts = vec![
+        format!("id `{}`", memory.id),
+        format!("instance `{}`", memory.instance_id),
+        format!("created {}", memory.created_at.to_rfc3339()),
+        format!("importance {}", memory.metadata.importance),
+    ];
+    if let Some(ref category) = memory.metadata.category {
+        parts.push(format!("category `{}`", category));
+    }
+    if !memory.metadata.tags.is_empty() {
+        let tags: Vec<String> = memory.metadata.tags.iter().map(|t| format!("`{}`", t)).collect();
+        parts.push(format!("tags {}", tags.join(", ")));
+    }
+    if let Some(ref framework) = memory.metadata.framework {
+        parts.push(format!("framework `{}`", framework));
+    }
+    parts.join(" · ")
+}
+
+/// Double-quoted YAML scalar; JSON string escaping is valid YAML
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
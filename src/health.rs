@@ -0,0 +1,72 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::service::UnifiedRagService;
+
+/// Minimal HTTP/1.1 server for container probes, independent of the MCP transport.
+///
+/// `GET /healthz` answers 200 while the process is up; `GET /readyz` checks Redis,
+/// Qdrant and OpenAI and answers 200 or 503 with a JSON status per backend.
+pub async fn serve(addr: String, service: UnifiedRagService) {
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to bind health endpoint on {}: {}", addr, e);
+            return;
+        }
+    };
+    tracing::info!("Health endpoints listening on http://{}", addr);
+    
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &service).await {
+                        tracing::debug!("Health request failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("Health endpoint accept failed: {}", e),
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, service: &UnifiedRagService) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
+    
+    let (status, body) = match (method, path) {
+        ("GET", "/healthz") => ("200 OK", serde_json::json!({ "status": "ok" })),
+        ("GET", "/readyz") => {
+            let checks = service.readiness().await;
+            let ready = checks.iter().all(|(_, error)| error.is_none());
+            let backends: serde_json::Map<String, serde_json::Value> = checks
+                .into_iter()
+                .map(|(name, error)| {
+                    let status = match error {
+                        None => "ok".to_string(),
+                        Some(e) => format!("error: {}", e),
+                    };
+                    (name.to_string(), serde_json::Value::String(status))
+                })
+                .collect();
+            let status = if ready { "200 OK" } else { "503 Service Unavailable" };
+            (status, serde_json::json!({ "ready": ready, "backends": backends }))
+        }
+        ("GET", _) => ("404 Not Found", serde_json::json!({ "error": "not found" })),
+        _ => ("405 Method Not Allowed", serde_json::json!({ "error": "method not allowed" })),
+    };
+    
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
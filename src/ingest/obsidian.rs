@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+/// A markdown note read from an Obsidian vault, with its frontmatter split off
+#[derive(Debug, Clone)]
+pub struct VaultNote {
+    pub body: String,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+}
+
+/// Recursively collect `.md` files under `root`, skipping dot-directories like `.obsidian` and `.trash`
+pub fn find_markdown_files(root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let file_type = entry.file_type()?;
+            
+            if file_type.is_dir() && !hidden {
+                pending.push(path);
+            } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+                files.push(path);
+            }
+        }
+    }
+    
+    files.sort();
+    Ok(files)
+}
+
+/// Split a note into frontmatter fields and body.
+///
+/// Only `tags`/`tag` and `category` are read from the frontmatter. Tags may be
+/// an inline list (`[a, b]`), a comma-separated string, or a `- item` block list;
+/// a leading `#` is dropped.
+pub fn parse_note(raw: &str) -> VaultNote {
+    let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+    let mut tags = Vec::new();
+    let mut category = None;
+    
+    let (frontmatter, body) = match raw.strip_prefix("---\n").or_else(|| raw.strip_prefix("---\r\n")) {
+        Some(rest) => match rest.find("\n---") {
+            Some(end) => {
+                let after = &rest[end + 4..];
+                let body = after.split_once('\n').map(|(_, b)| b).unwrap_or("");
+                (Some(&rest[..end]), body)
+            }
+            None => (None, raw),
+        },
+        None => (None, raw),
+    };
+    
+    if let Some(frontmatter) = frontmatter {
+        let mut in_tag_list = false;
+        for line in frontmatter.lines() {
+            let trimmed = line.trim();
+            
+            if in_tag_list {
+                if let Some(item) = trimmed.strip_prefix("- ") {
+                    push_tag(&mut tags, item);
+                    continue;
+                }
+                in_tag_list = false;
+            }
+            
+            let Some((key, value)) = trimmed.split_once(':') else { continue };
+            let value = value.trim();
+            match key.trim() {
+                "tags" | "tag" => {
+                    if value.is_empty() {
+                        in_tag_list = true;
+                    } else {
+                        let value = value.trim_start_matches('[').trim_end_matches(']');
+                        for item in value.split(',') {
+                            push_tag(&mut tags, item);
+                        }
+                    }
+                }
+                "category" if !value.is_empty() => {
+                    category = Some(unquote(value).to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    VaultNote {
+        body: body.trim().to_string(),
+        tags,
+        category,
+    }
+}
+
+fn push_tag(tags: &mut Vec<String>, raw: &str) {
+    let tag = unquote(raw.trim()).trim_start_matches('#').trim();
+    if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+        tags.push(tag.to_string());
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches(|c| c == '"' || c == '\'')
+}
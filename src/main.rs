@@ -1,6 +1,6 @@
 use anyhow::Result;
 use rmcp::ServiceExt;
-use tracing_subscriber;
+use tracing_subscriber::EnvFilter;
 
 mod config;
 mod models;
@@ -9,18 +9,19 @@ mod search;
 mod tools;
 mod service;
 mod error;
-mod transport_wrapper;
+mod text;
+mod ingest;
+mod export;
+mod health;
+mod ratelimit;
+mod tokenizer;
 
 use crate::service::UnifiedRagService;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing to stderr for MCP compatibility
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .with_ansi(false)
-        .with_writer(std::io::stderr)
-        .init();
+    init_tracing();
 
     tracing::info!("Starting UnifiedRAG MCP server");
 
@@ -36,6 +37,17 @@ async fn main() -> Result<()> {
         }
     };
     
+    // Probe endpoints for orchestrators, only when HEALTH_ADDR is set
+    if let Some(addr) = service.health_addr() {
+        tokio::spawn(health::serve(addr.to_string(), service.clone()));
+    }
+    
+    // Retries embeddings deferred while OpenAI was unreachable, when DEFER_FAILED_EMBEDDINGS is set
+    tokio::spawn(service.clone().run_embedding_queue());
+    
+    // Optional, gated by WARM_CACHE since it costs startup time and embedding calls
+    service.warm_cache().await;
+    
     // Log that we're about to start serving
     tracing::info!("About to start serving on stdio transport");
     
@@ -57,4 +69,23 @@ async fn main() -> Result<()> {
     
     tracing::info!("UnifiedRAG MCP server shutting down");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Log to stderr (stdout carries the MCP protocol). The filter comes from `RUST_LOG`,
+/// else `LOG_LEVEL`, else `info`; `LOG_FORMAT=json` emits one JSON object per line.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string())))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .with_ansi(false)
+        .with_writer(std::io::stderr);
+    
+    match std::env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => builder.json().init(),
+        _ => builder.init(),
+    }
+}
@@ -1,21 +1,69 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use rmcp::schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A stored memory, as kept in Redis and in Qdrant payloads.
+///
+/// Records outlive deployments, so only `id`, `content`, `created_at` and
+/// `updated_at` are required. Anything else missing from an older record
+/// deserializes to its default: an empty `instance_id`, no `embedding`/`summary`,
+/// `access_count` and `relevance_score` of 0, and `MemoryMetadata::default()`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Memory {
     pub id: Uuid,
+    #[serde(default)]
     pub instance_id: String,
     pub content: String,
+    #[serde(default)]
     pub embedding: Option<Vec<f32>>,
+    #[serde(default)]
     pub metadata: MemoryMetadata,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
     pub access_count: u64,
+    #[serde(default)]
     pub relevance_score: f32,
+    /// Short summary embedded under the `summary` named vector
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Set on search results whose content was cut to `max_content_chars`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Best-matching sentence, returned when a search asks for snippets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+    /// Which tag/category filters a filtered search result satisfied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_match: Option<FilterMatch>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Passage of a memory's content, with character offsets into the full content
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Snippet {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Why a result passed a search's filters, for highlighting the match reason
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FilterMatch {
+    /// The memory's tags that appear in `tags_filter`, when tags were filtered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Whether the memory's category satisfies `category_filter`, when a category was filtered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<bool>,
+}
+
+/// Fields missing from older stored records take their `Default` values: no
+/// category, chain, parent, framework or content hash, no tags or extra fields,
+/// importance 5 (the `rag_store` default) and an empty `source`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct MemoryMetadata {
     pub category: Option<String>,
     pub tags: Vec<String>,
@@ -24,6 +72,29 @@ pub struct MemoryMetadata {
     pub parent_id: Option<Uuid>,
     pub framework: Option<String>,
     pub source: String,
+    /// sha256 of the normalized content, for exact-duplicate and change detection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Caller-defined fields (e.g. `author`, `url`), stored as given and filterable
+    /// with `extra_filters`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl Default for MemoryMetadata {
+    fn default() -> Self {
+        Self {
+            category: None,
+            tags: Vec::new(),
+            importance: 5,
+            chain_id: None,
+            parent_id: None,
+            framework: None,
+            source: String::new(),
+            content_hash: None,
+            extra: BTreeMap::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,12 +103,162 @@ pub struct SearchRequest {
     pub limit: Option<usize>,
     pub threshold: Option<f32>,
     pub category_filter: Option<String>,
+    pub category_match_mode: CategoryMatchMode,
     pub tags_filter: Option<Vec<String>>,
+    /// Require at least this many of `tags_filter` instead of all (Qdrant) / any (cache)
+    pub tag_min_match: Option<usize>,
     pub instance_filter: Option<Vec<String>>,
-    pub hybrid_mode: bool,
+    /// Only memories on this chain (`metadata.chain_id`)
+    pub chain_filter: Option<String>,
+    /// Only memories whose `metadata.extra` has each of these values (string, integer or bool)
+    pub extra_filters: Option<BTreeMap<String, serde_json::Value>>,
+    pub mode: SearchMode,
+    pub vector: VectorName,
+    /// Skip reading the result cache; fresh results still replace the cached entry
+    pub bypass_cache: bool,
+    /// Seconds to keep this query's cached result instead of `SEARCH_CACHE_TTL`; 0 skips the cache
+    pub cache_ttl: Option<u64>,
+    /// Search every registered instance's cache instead of only this one's
+    pub all_instances: bool,
+    /// Qdrant HNSW `ef` for this query; higher trades speed for recall
+    pub hnsw_ef: Option<u64>,
+    /// Memories to leave out of the results, e.g. one the caller just stored
+    pub exclude_ids: Vec<Uuid>,
+    /// Only memories whose content contains every word of this text (Qdrant full-text match)
+    pub text_match: Option<String>,
+    /// Embedding model for the query instead of `EMBEDDING_MODEL`
+    pub model: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl SearchRequest {
+    /// Stable string identifying which results this request asks for, for the result
+    /// cache and anything else that dedups queries. Filter lists are sorted, the query
+    /// is NFC-normalized and prefix category filters lose their `/*`, so logically equal
+    /// requests agree. Options that don't change the results (`bypass_cache`,
+    /// `cache_ttl`, `hnsw_ef`) are left out.
+    pub fn canonical_cache_key(&self) -> String {
+        let sorted = |values: &Option<Vec<String>>| {
+            values.as_ref().map(|values| {
+                let mut values = values.clone();
+                values.sort();
+                values
+            })
+        };
+        let mut excluded = self.exclude_ids.clone();
+        excluded.sort();
+        
+        serde_json::json!({
+            "query": crate::text::normalize(&self.query),
+            "limit": self.limit,
+            "threshold": self.threshold,
+            "category_filter": self.category_filter.as_deref().map(|c| self.category_match_mode.normalize(c)),
+            "category_match_mode": self.category_match_mode,
+            "tags_filter": sorted(&self.tags_filter),
+            "tag_min_match": self.tag_min_match,
+            "instance_filter": sorted(&self.instance_filter),
+            "chain_filter": self.chain_filter,
+            "extra_filters": self.extra_filters,
+            "mode": self.mode,
+            "vector": self.vector,
+            "all_instances": self.all_instances,
+            "exclude_ids": excluded,
+            "text_match": self.text_match,
+            "model": self.model,
+        })
+        .to_string()
+    }
+    
+    /// Which of this request's tag and category filters `memory` satisfies;
+    /// `None` when neither filter is set
+    pub fn filter_match(&self, memory: &Memory) -> Option<FilterMatch> {
+        if self.tags_filter.is_none() && self.category_filter.is_none() {
+            return None;
+        }
+        
+        Some(FilterMatch {
+            tags: self.tags_filter.as_ref().map(|filter| {
+                memory.metadata.tags.iter().filter(|tag| filter.contains(tag)).cloned().collect()
+            }),
+            category: self.category_filter.as_deref().map(|filter| {
+                memory.metadata.category.as_deref().is_some_and(|c| self.category_match_mode.matches(filter, c))
+            }),
+        })
+    }
+}
+
+/// Which backends `rag_search` queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Redis keyword search and Qdrant semantic search, fused
+    #[default]
+    Hybrid,
+    /// Qdrant semantic search only
+    SemanticOnly,
+    /// Redis metadata filters ranked by keyword overlap; no embedding, so no OpenAI call
+    CacheOnly,
+}
+
+/// How `category_filter` compares against a memory's category
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryMatchMode {
+    #[default]
+    Exact,
+    /// Match the category and everything below it in a `/`-separated hierarchy
+    Prefix,
+}
+
+impl CategoryMatchMode {
+    /// The filter with any trailing `/*` or `/` removed in prefix mode
+    pub fn normalize<'a>(&self, filter: &'a str) -> &'a str {
+        match self {
+            CategoryMatchMode::Exact => filter,
+            CategoryMatchMode::Prefix => filter.trim_end_matches('*').trim_end_matches('/'),
+        }
+    }
+    
+    /// Whole segments only: prefix `project` matches `project/foo` but not `projects`
+    pub fn matches(&self, filter: &str, category: &str) -> bool {
+        let filter = self.normalize(filter);
+        match self {
+            CategoryMatchMode::Exact => category == filter,
+            CategoryMatchMode::Prefix => category
+                .strip_prefix(filter)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        }
+    }
+}
+
+/// Every ancestor of a `/`-separated category, itself included:
+/// `project/foo/bar` gives `project`, `project/foo`, `project/foo/bar`
+pub fn category_prefixes(category: &str) -> Vec<String> {
+    category
+        .match_indices('/')
+        .map(|(i, _)| category[..i].to_string())
+        .chain(std::iter::once(category.to_string()))
+        .collect()
+}
+
+/// Named vector in the Qdrant collection a search runs against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorName {
+    #[default]
+    Content,
+    Summary,
+}
+
+impl VectorName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorName::Content => "content",
+            VectorName::Summary => "summary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     pub memories: Vec<Memory>,
     pub search_id: Uuid,
@@ -45,6 +266,41 @@ pub struct SearchResult {
     pub cache_hits: usize,
     pub total_results: usize,
     pub search_time_ms: u64,
+    /// Set when a backend failed and the results come from the others only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Which backend failed and why, when `partial` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Where the search spent its time, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<SearchTimings>,
+    /// The Redis keyword search hit `CACHE_SEARCH_BUDGET_MS`, so keyword matches may be missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Results were dropped from the end of the page to keep the response under `MAX_RESPONSE_BYTES`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub response_truncated: bool,
+    /// `offset` that continues after the last returned result, when `response_truncated` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+}
+
+/// Wall-clock milliseconds per search stage. In hybrid mode the cache lookup runs
+/// concurrently with embedding + Qdrant, so stages can sum to more than `total_ms`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchTimings {
+    /// Query embedding, including the embedding cache lookup
+    pub embedding_ms: u64,
+    /// Redis keyword search (hybrid mode only)
+    pub cache_ms: u64,
+    /// Qdrant vector search
+    pub qdrant_ms: u64,
+    /// Loading full memories for Qdrant hits from Redis (`redis_primary` mode)
+    pub hydrate_ms: u64,
+    /// Cross-encoder rerank, when requested
+    pub rerank_ms: u64,
+    pub total_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,12 +312,611 @@ pub struct StoreRequest {
     pub chain_id: Option<String>,
     pub parent_id: Option<Uuid>,
     pub framework: Option<String>,
+    /// Precomputed embedding; skips generation when present
+    pub embedding: Option<Vec<f32>>,
+    /// Summary to embed under the `summary` vector
+    pub summary: Option<String>,
+    /// Skip storing when this instance already has a memory at or above `DEDUP_THRESHOLD`
+    pub dedup: bool,
+    /// Embed only the first `MAX_CONTENT_TOKENS` of over-long content; the full content is still stored
+    pub truncate: bool,
+    /// Set the nearest existing memory at or above `AUTO_LINK_THRESHOLD` as `parent_id`
+    pub auto_link: bool,
+    /// Embed the content with Markdown syntax stripped; the content is stored as given
+    pub strip_markdown: bool,
+    /// Embed the content with HTML tags stripped; the content is stored as given
+    pub strip_html: bool,
+    /// Caller-defined metadata fields
+    pub extra: BTreeMap<String, serde_json::Value>,
+    /// Embedding model for this store instead of `EMBEDDING_MODEL`
+    pub model: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Whether a stored extra field satisfies an `extra_filters` value: equal, or an
+/// array containing it, as Qdrant matches array payloads
+pub fn extra_matches(stored: Option<&serde_json::Value>, wanted: &serde_json::Value) -> bool {
+    match stored {
+        Some(serde_json::Value::Array(values)) => values.contains(wanted),
+        Some(value) => value == wanted,
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StoreResult {
     pub memory_id: Uuid,
     pub cached: bool,
     pub indexed: bool,
     pub embedding_generated: bool,
-}
\ No newline at end of file
+    /// `memory_id` is an existing near-identical memory and nothing new was stored
+    #[serde(default)]
+    pub duplicate: bool,
+    /// Every stored chunk when over-long content was split; `memory_id` is the first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunk_ids: Vec<Uuid>,
+    /// Only the first `MAX_CONTENT_TOKENS` of the content were embedded
+    #[serde(default)]
+    pub embedding_truncated: bool,
+    /// Embedding failed; the memory is in Redis only and queued to be embedded and indexed later
+    #[serde(default)]
+    pub embedding_deferred: bool,
+    /// Content was under `MIN_EMBED_CHARS`; the memory is in Redis only and never embedded
+    #[serde(default)]
+    pub embedding_skipped: bool,
+    /// Memory `auto_link` adopted as this one's parent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_id: Option<Uuid>,
+    /// Tags past `MAX_TAGS` were dropped
+    #[serde(default)]
+    pub tags_truncated: bool,
+    /// SHA-256 of the stored (normalized) content; absent for duplicates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Where the store spent its time, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StoreTimings>,
+}
+
+/// Wall-clock milliseconds per store stage; chunked stores add up their chunks
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreTimings {
+    /// Content embedding, including the embedding cache lookup; 0 when a vector was passed in
+    pub embedding_ms: u64,
+    /// Writing the memory to Redis, and queueing it when its embedding was deferred
+    pub redis_ms: u64,
+    /// Dedup and auto_link lookups plus the upsert
+    pub qdrant_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StoreAndRelateResult {
+    #[serde(flatten)]
+    pub store: StoreResult,
+    /// Most similar other memories, best first; excludes the stored memory and its chunks
+    pub neighbors: Vec<Memory>,
+    /// Why `neighbors` is empty although the store succeeded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Payload field a `rag_aggregate` call groups by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateField {
+    Category,
+    Tag,
+    Framework,
+    Source,
+}
+
+/// Metadata filter selecting memories for bulk operations; fields combine with AND
+/// and match as in `SearchRequest`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryFilter {
+    pub category_filter: Option<String>,
+    pub category_match_mode: CategoryMatchMode,
+    pub tags_filter: Option<Vec<String>>,
+    pub tag_min_match: Option<usize>,
+    pub instance_filter: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateRequest {
+    pub group_by: AggregateField,
+    pub category_filter: Option<String>,
+    pub tags_filter: Option<Vec<String>>,
+    pub instance_filter: Option<Vec<String>>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AggregateBucket {
+    pub key: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AggregateResult {
+    pub group_by: AggregateField,
+    pub groups: Vec<AggregateBucket>,
+    pub total_memories: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CollectionInfoResult {
+    /// Physical collection currently serving searches
+    pub collection: String,
+    /// Alias the service reads and writes through, when it uses one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// green, yellow, grey or red
+    pub status: String,
+    pub optimizer_ok: bool,
+    /// Optimizer error message, when `optimizer_ok` is false
+    pub optimizer_error: Option<String>,
+    pub points_count: Option<u64>,
+    pub indexed_vectors_count: Option<u64>,
+    pub segments_count: u64,
+    pub vectors: Vec<VectorInfo>,
+    /// Payload field -> index type, for every payload index
+    pub payload_indexes: BTreeMap<String, String>,
+    /// Whether each payload field search filters on has an index
+    pub filter_fields_indexed: BTreeMap<String, bool>,
+}
+
+/// A Qdrant collection snapshot on the node the service talks to
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub size_bytes: u64,
+    /// SHA256 of the snapshot file, when Qdrant reports one
+    pub checksum: Option<String>,
+    /// Path of the snapshot on Qdrant's HTTP API, for downloading or restoring it
+    pub location: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotResult {
+    pub collection: String,
+    pub snapshot: SnapshotInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotsResult {
+    pub collection: String,
+    /// Newest first
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+/// Outcome of one `rag_validate` check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but slower or with reduced features
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ValidationCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix a failing or warning check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ValidateResult {
+    /// Collection or alias named by `QDRANT_COLLECTION`
+    pub collection: String,
+    /// False when any check failed; warnings don't fail validation
+    pub passed: bool,
+    pub checks: Vec<ValidationCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CountTokensResult {
+    pub tokens: usize,
+    pub model: String,
+    /// tiktoken encoding the model uses, e.g. `cl100k_base`
+    pub encoding: String,
+    /// False when the encoding couldn't be loaded and `tokens` is the conservative estimate
+    pub exact: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VectorInfo {
+    /// Vector name; empty for a single unnamed vector
+    pub name: String,
+    pub size: u64,
+    pub distance: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportVaultResult {
+    pub files_scanned: usize,
+    pub notes_imported: usize,
+    pub notes_unchanged: usize,
+    pub memories_stored: usize,
+    pub failures: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportCsvResult {
+    pub path: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportChainResult {
+    pub chain_id: String,
+    /// Chain entries rendered from a stored memory
+    pub memories: usize,
+    /// Chain entries whose memory could not be found, rendered as placeholders
+    pub missing: usize,
+    /// Where the document was written, when a path was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The document itself, when no path was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MigrateModelResult {
+    pub model: String,
+    /// Physical collection the memories were read from
+    pub source_collection: String,
+    pub target_collection: String,
+    /// The new model's vector size; not probed on a dry run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<u64>,
+    /// What the migration would send to the embedding API, on a dry run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<EmbeddingEstimate>,
+    /// Memories embedded and indexed into the target by this run
+    pub migrated: usize,
+    /// Memories a previous run already indexed into the target
+    pub already_migrated: usize,
+    /// Memories that could not be loaded, embedded or indexed; rerun to retry them
+    pub failed: usize,
+    /// The first few failure messages
+    pub errors: Vec<String>,
+    /// Alias now pointing at the target, when one was requested and nothing failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Scroll pages migrated at once
+    pub concurrency: usize,
+    /// Wall-clock time of the run
+    pub elapsed_ms: u64,
+    /// Memories migrated per second of `elapsed_ms`
+    pub memories_per_sec: f64,
+}
+
+/// Embedding work for re-embedding every memory, counted without calling the API
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddingEstimate {
+    /// Memories whose content would be embedded
+    pub memories: usize,
+    /// Summaries embedded separately under the `summary` vector
+    pub summaries: usize,
+    /// Memories whose content couldn't be loaded; they are not counted
+    pub missing_content: usize,
+    /// Memories above `MAX_CONTENT_TOKENS`, which the API is likely to reject
+    pub over_token_limit: usize,
+    /// Tokens counted with the model's encoding, or estimated when `exact` is false
+    pub estimated_tokens: u64,
+    /// False when the model's encoding couldn't be loaded and the conservative
+    /// `estimate_tokens` was used instead; real counts are then usually lower
+    pub exact: bool,
+    /// Embedding API requests: content in batches of `EMBEDDING_BATCH_SIZE` per page, one per summary
+    pub requests: usize,
+    /// Price per million tokens, when the model's price is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usd_per_million_tokens: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClearResult {
+    pub instance_id: String,
+    pub redis_keys_removed: u64,
+    pub qdrant_points_removed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteByFilterResult {
+    pub instance_id: String,
+    /// Matching Qdrant points, resolved before deleting; each id was also removed from Redis
+    pub memories_matched: u64,
+    pub qdrant_points_removed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveResult {
+    pub memory_id: Uuid,
+    pub from_instance: String,
+    pub to_instance: String,
+    pub cached: bool,
+    pub indexed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReembedResult {
+    pub memory_id: Uuid,
+    /// The memory now has a content vector in Qdrant
+    pub indexed: bool,
+    /// The point was missing from Qdrant (deferred or skipped embedding) and was indexed in full
+    pub created: bool,
+    /// Only the first `MAX_CONTENT_TOKENS` of the content were embedded
+    pub embedding_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkResult {
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    pub relation: String,
+    /// False when the two memories were already linked under this relation
+    pub created: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnlinkResult {
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    /// Relations removed between the two memories; empty when none matched
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Relation {
+    /// The linked memory
+    pub id: Uuid,
+    pub relation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelationsResult {
+    pub memory_id: Uuid,
+    /// Links sorted by id, then relation
+    pub relations: Vec<Relation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagsResult {
+    pub memory_id: Uuid,
+    /// The memory's tags after the edit
+    pub tags: Vec<String>,
+    /// False when every tag was already present (add) or absent (remove)
+    pub changed: bool,
+    pub cached: bool,
+    pub indexed: bool,
+    /// Added tags past `MAX_TAGS` were dropped
+    pub tags_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagCooccurrenceResult {
+    pub tag: String,
+    /// Thoughts in the tag's index set whose metadata was found
+    pub memories: u64,
+    /// Co-occurring tags, most frequent first
+    pub cooccurring: Vec<TagCooccurrence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagCooccurrence {
+    pub tag: String,
+    /// Thoughts carrying both tags
+    pub count: u64,
+    /// Jaccard index of the two tag sets: count / (|tag| + |other| - count)
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SuggestTagsResult {
+    /// Similar memories of this instance the suggestions were drawn from
+    pub memories_considered: usize,
+    /// Suggested tags, most relevant first
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// Share of the considered memories' similarity carried by memories with this tag (0-1)
+    pub score: f64,
+    /// Considered memories carrying the tag
+    pub matches: usize,
+    /// Size of the tag's index set in this instance
+    pub tagged: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetManyResult {
+    /// One entry per requested id, in request order; `null` where no memory was found.
+    /// Embeddings are left out.
+    pub memories: Vec<Option<Memory>>,
+    pub found: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MergeResult {
+    /// The primary memory after the merge, without its embedding
+    pub memory: Memory,
+    pub merged_ids: Vec<Uuid>,
+    /// The union of tags went past `MAX_TAGS` and the rest were dropped
+    pub tags_truncated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn search_request() -> SearchRequest {
+        SearchRequest {
+            query: "release notes".to_string(),
+            limit: Some(20),
+            threshold: Some(0.7),
+            category_filter: None,
+            category_match_mode: CategoryMatchMode::default(),
+            tags_filter: None,
+            tag_min_match: None,
+            instance_filter: None,
+            chain_filter: None,
+            extra_filters: None,
+            mode: SearchMode::default(),
+            vector: VectorName::default(),
+            bypass_cache: false,
+            cache_ttl: None,
+            all_instances: false,
+            hnsw_ef: None,
+            exclude_ids: Vec::new(),
+            text_match: None,
+            model: None,
+        }
+    }
+    
+    #[test]
+    fn canonical_cache_key_ignores_filter_order() {
+        let first = Uuid::parse_str("6f1c2a4e-8b1d-4c3e-9f0a-2b5d7e9c1a3f").unwrap();
+        let second = Uuid::parse_str("0b6e1f52-3c4d-4e8a-9b7c-1d2e3f4a5b6c").unwrap();
+        let a = SearchRequest {
+            tags_filter: Some(vec!["rust".to_string(), "async".to_string(), "redis".to_string()]),
+            instance_filter: Some(vec!["CC".to_string(), "DT".to_string()]),
+            exclude_ids: vec![first, second],
+            ..search_request()
+        };
+        let b = SearchRequest {
+            tags_filter: Some(vec!["redis".to_string(), "rust".to_string(), "async".to_string()]),
+            instance_filter: Some(vec!["DT".to_string(), "CC".to_string()]),
+            exclude_ids: vec![second, first],
+            // Options that don't change the results stay out of the key
+            bypass_cache: true,
+            cache_ttl: Some(60),
+            hnsw_ef: Some(256),
+            ..search_request()
+        };
+        assert_eq!(a.canonical_cache_key(), b.canonical_cache_key());
+        
+        let other_tags = SearchRequest {
+            tags_filter: Some(vec!["rust".to_string()]),
+            ..a.clone()
+        };
+        assert_ne!(a.canonical_cache_key(), other_tags.canonical_cache_key());
+    }
+    
+    #[test]
+    fn minimal_payload_takes_defaults() {
+        let memory: Memory = serde_json::from_value(json!({
+            "id": "6f1c2a4e-8b1d-4c3e-9f0a-2b5d7e9c1a3f",
+            "content": "remember this",
+            "created_at": "2024-01-02T03:04:05Z",
+            "updated_at": "2024-01-02T03:04:05Z",
+        }))
+        .unwrap();
+        
+        assert_eq!(memory.content, "remember this");
+        assert_eq!(memory.instance_id, "");
+        assert!(memory.embedding.is_none());
+        assert!(memory.summary.is_none());
+        assert_eq!(memory.access_count, 0);
+        assert_eq!(memory.relevance_score, 0.0);
+        assert!(!memory.truncated);
+        assert_eq!(memory.metadata.importance, 5);
+        assert!(memory.metadata.tags.is_empty());
+        assert!(memory.metadata.category.is_none());
+        assert_eq!(memory.metadata.source, "");
+    }
+    
+    /// Payloads as earlier versions wrote them: the original schema with every field
+    /// present, then one from before `summary` and `content_hash` with a null embedding
+    const HISTORICAL_PAYLOADS: [&str; 2] = [
+        r#"{
+            "id": "0b6e1f52-3c4d-4e8a-9b7c-1d2e3f4a5b6c",
+            "instance_id": "CC",
+            "content": "original schema",
+            "embedding": [0.25, -0.5],
+            "metadata": {
+                "category": "notes",
+                "tags": ["a", "b"],
+                "importance": 8,
+                "chain_id": "chain-1",
+                "parent_id": null,
+                "framework": "OODA",
+                "source": "rag_store"
+            },
+            "created_at": "2024-05-06T07:08:09Z",
+            "updated_at": "2024-05-06T07:08:09Z",
+            "access_count": 3,
+            "relevance_score": 0.0
+        }"#,
+        r#"{
+            "id": "9a8b7c6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d",
+            "instance_id": "DT",
+            "content": "before summaries",
+            "embedding": null,
+            "metadata": {"tags": [], "importance": 5, "source": "obsidian"},
+            "created_at": "2024-09-10T11:12:13.456Z",
+            "updated_at": "2024-09-11T00:00:00Z"
+        }"#,
+    ];
+    
+    #[test]
+    fn historical_payloads_round_trip() {
+        for payload in HISTORICAL_PAYLOADS {
+            let memory: Memory = serde_json::from_str(payload).unwrap();
+            let again: Memory = serde_json::from_value(serde_json::to_value(&memory).unwrap()).unwrap();
+            
+            assert_eq!(again.id, memory.id);
+            assert_eq!(again.instance_id, memory.instance_id);
+            assert_eq!(again.content, memory.content);
+            assert_eq!(again.embedding, memory.embedding);
+            assert_eq!(again.created_at, memory.created_at);
+            assert_eq!(again.updated_at, memory.updated_at);
+            assert_eq!(again.access_count, memory.access_count);
+            assert_eq!(again.metadata.tags, memory.metadata.tags);
+            assert_eq!(again.metadata.importance, memory.metadata.importance);
+            assert_eq!(again.metadata.chain_id, memory.metadata.chain_id);
+            assert_eq!(again.metadata.source, memory.metadata.source);
+            assert!(again.summary.is_none());
+            assert!(again.metadata.content_hash.is_none());
+        }
+        
+        let original: Memory = serde_json::from_str(HISTORICAL_PAYLOADS[0]).unwrap();
+        assert_eq!(original.embedding, Some(vec![0.25, -0.5]));
+        assert_eq!(original.metadata.framework.as_deref(), Some("OODA"));
+        let before_summaries: Memory = serde_json::from_str(HISTORICAL_PAYLOADS[1]).unwrap();
+        assert!(before_summaries.metadata.category.is_none());
+        assert_eq!(before_summaries.access_count, 0);
+    }
+    
+    #[test]
+    fn payload_missing_a_required_field_is_rejected() {
+        let result = serde_json::from_value::<Memory>(json!({
+            "id": "6f1c2a4e-8b1d-4c3e-9f0a-2b5d7e9c1a3f",
+            "created_at": "2024-01-02T03:04:05Z",
+            "updated_at": "2024-01-02T03:04:05Z",
+        }));
+        assert!(result.is_err());
+    }
+}
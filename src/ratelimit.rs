@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Per-tool token buckets, refilled continuously at each tool's requests/sec.
+///
+/// Tools without a configured limit are never throttled. Each bucket holds up to
+/// one second's worth of requests (at least one), so short bursts pass.
+pub struct RateLimiter {
+    limits: HashMap<String, f64>,
+    default_limit: Option<f64>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<String, f64>, default_limit: Option<f64>) -> Self {
+        Self {
+            limits,
+            default_limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `tool`, or return the configured rate when the bucket is empty
+    pub fn check(&self, tool: &str) -> Result<(), f64> {
+        let Some(rate) = self.limits.get(tool).copied().or(self.default_limit) else {
+            return Ok(());
+        };
+        let capacity = rate.max(1.0);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(tool.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(rate)
+        }
+    }
+}
@@ -1,36 +1,134 @@
-use async_openai::{Client, config::OpenAIConfig};
+use async_openai::{Client, config::OpenAIConfig, error::OpenAIError};
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use async_openai::types::{CreateEmbeddingRequestArgs, EmbeddingInput};
+use crate::config::DEFAULT_EMBEDDING_MODEL;
 use crate::error::{Result, UnifiedRagError};
+use crate::text;
+use ring::digest::{digest, SHA256};
+
+/// List price in USD per million input tokens for OpenAI's embedding models
+pub fn price_per_million_tokens(model: &str) -> Option<f64> {
+    match model {
+        "text-embedding-3-small" => Some(0.02),
+        "text-embedding-3-large" => Some(0.13),
+        "text-embedding-ada-002" => Some(0.10),
+        _ => None,
+    }
+}
 
 pub struct EmbeddingGenerator {
-    client: Client<OpenAIConfig>,
+    /// None without `OPENAI_API_KEY`: every embedding call then fails with `EmbeddingsUnavailable`
+    client: Option<Client<OpenAIConfig>>,
     model: String,
+    batch_size: usize,
+    batch_concurrency: usize,
+    /// Caps sub-batch requests in flight across every concurrent `generate_embeddings` call
+    batch_permits: Arc<Semaphore>,
+    /// Set by `HASH_EMBEDDINGS`: embed locally with `hash_embedding` at this dimension
+    /// instead of calling the API
+    hash_dimension: Option<usize>,
 }
 
 impl EmbeddingGenerator {
-    pub fn new() -> Result<Self> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| UnifiedRagError::Configuration("OPENAI_API_KEY not set".to_string()))?;
-        
-        let config = OpenAIConfig::new().with_api_key(api_key);
-        let client = Client::with_config(config);
+    /// Client for the embeddings API; each HTTP request is cut off after `timeout`.
+    /// Without `OPENAI_API_KEY` no client is built, so cache-only work still runs.
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = match std::env::var("OPENAI_API_KEY").ok().filter(|key| !key.trim().is_empty()) {
+            Some(api_key) => {
+                let http_client = reqwest::Client::builder()
+                    .timeout(timeout)
+                    .build()
+                    .map_err(|e| UnifiedRagError::Configuration(format!("Failed to build OpenAI HTTP client: {}", e)))?;
+                let config = OpenAIConfig::new().with_api_key(api_key);
+                Some(Client::with_config(config).with_http_client(http_client))
+            }
+            None => None,
+        };
         
         Ok(Self {
             client,
-            model: "text-embedding-3-small".to_string(),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            batch_size: 100,
+            batch_concurrency: 1,
+            batch_permits: Arc::new(Semaphore::new(1)),
+            hash_dimension: None,
         })
     }
     
+    /// Embed every text locally with `hash_embedding` instead of calling the API,
+    /// whatever the model; for tests and offline development
+    pub fn with_hash_embeddings(mut self, dimension: usize) -> Self {
+        self.hash_dimension = Some(dimension.max(1));
+        self
+    }
+    
+    /// Split `generate_embeddings` input into requests of `batch_size`, running up to
+    /// `concurrency` of them at once, however many callers share the generator
+    pub fn with_batching(mut self, batch_size: usize, concurrency: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self.batch_concurrency = concurrency.max(1);
+        self.batch_permits = Arc::new(Semaphore::new(self.batch_concurrency));
+        self
+    }
+    
+    /// Embed with `model` instead of the default
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+    
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    
+    /// Whether an API key was configured (or hashing is on), so embedding calls can be made at all
+    pub fn is_available(&self) -> bool {
+        self.client.is_some() || self.hash_dimension.is_some()
+    }
+    
+    fn client(&self) -> Result<&Client<OpenAIConfig>> {
+        self.client
+            .as_ref()
+            .ok_or_else(|| UnifiedRagError::EmbeddingsUnavailable("no API key (set OPENAI_API_KEY)".to_string()))
+    }
+    
+    /// Check that the API is reachable and the key can see the model, without embedding anything
+    pub async fn check_connection(&self) -> Result<()> {
+        if self.hash_dimension.is_some() {
+            return Ok(());
+        }
+        self.client()?.models().retrieve(&self.model).await.map_err(classify_error)?;
+        Ok(())
+    }
+    
+    /// Embed a short probe string to learn the model's output dimension
+    pub async fn probe_dimension(&self) -> Result<u64> {
+        let embedding = self.generate_embedding("dimension probe").await?;
+        Ok(embedding.len() as u64)
+    }
+    
     pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.generate_embedding_with(&self.model, text).await
+    }
+    
+    /// Embed `text` with `model` instead of the generator's own, over the same client
+    pub async fn generate_embedding_with(&self, model: &str, text: &str) -> Result<Vec<f32>> {
+        if let Some(dimension) = self.hash_dimension {
+            return Ok(hash_embedding(&text::normalize(text), dimension));
+        }
         let request = CreateEmbeddingRequestArgs::default()
-            .model(&self.model)
-            .input(EmbeddingInput::String(text.to_string()))
+            .model(model)
+            .input(EmbeddingInput::String(text::normalize(text).into_owned()))
             .build()?;
         
-        let response = self.client
+        let response = self.client()?
             .embeddings()
             .create(request)
-            .await?;
+            .await
+            .map_err(classify_error)?;
         
         let embedding = response
             .data
@@ -42,28 +140,154 @@ impl EmbeddingGenerator {
         Ok(embedding)
     }
     
-    pub async fn generate_embeddings(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
-        let inputs: Vec<String> = texts
-            .into_iter()
-            .map(|t| t.to_string())
+    /// Embed many texts, one entry per input in input order.
+    ///
+    /// Inputs are sent in sub-batches of `batch_size`; a failed sub-batch only fails
+    /// its own entries, so the rest of a large batch is kept.
+    pub async fn generate_embeddings(&self, texts: Vec<&str>) -> Vec<Result<Vec<f32>>> {
+        let batches: Vec<Vec<String>> = texts
+            .chunks(self.batch_size)
+            .map(|chunk| chunk.iter().map(|t| text::normalize(t).into_owned()).collect())
             .collect();
         
+        let results: Vec<(usize, Result<Vec<Vec<f32>>>)> = stream::iter(batches.into_iter().enumerate())
+            .map(|(i, inputs)| async move {
+                let len = inputs.len();
+                (len, self.embed_batch(inputs).await.map_err(|e| {
+                    tracing::warn!("Embedding sub-batch {} ({} inputs) failed: {}", i, len, e);
+                    e
+                }))
+            })
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await;
+        
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for (len, result) in results {
+            match result {
+                Ok(batch) => embeddings.extend(batch.into_iter().map(Ok)),
+                Err(e) => {
+                    let message = e.to_string();
+                    embeddings.extend((0..len).map(|_| {
+                        Err(UnifiedRagError::SearchError(format!("Embedding batch failed: {}", message)))
+                    }));
+                }
+            }
+        }
+        embeddings
+    }
+    
+    /// One embeddings request for already-normalized inputs, ordered by response index
+    async fn embed_batch(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if let Some(dimension) = self.hash_dimension {
+            return Ok(inputs.iter().map(|input| hash_embedding(input, dimension)).collect());
+        }
+        let _permit = self.batch_permits
+            .acquire()
+            .await
+            .map_err(|e| UnifiedRagError::SearchError(format!("Embedding batch permits closed: {}", e)))?;
+        let expected = inputs.len();
         let request = CreateEmbeddingRequestArgs::default()
             .model(&self.model)
             .input(inputs)
             .build()?;
         
-        let response = self.client
+        let response = self.client()?
             .embeddings()
             .create(request)
-            .await?;
+            .await
+            .map_err(classify_error)?;
         
-        let embeddings = response
-            .data
-            .into_iter()
-            .map(|e| e.embedding)
-            .collect();
+        let mut data = response.data;
+        if data.len() != expected {
+            return Err(UnifiedRagError::SearchError(format!(
+                "Expected {} embeddings, got {}", expected, data.len()
+            )));
+        }
+        data.sort_by_key(|e| e.index);
+        Ok(data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+/// Deterministic, unit-length bag-of-words vector: each lowercased alphanumeric word
+/// adds ±1 at a position picked by its sha256. Texts sharing words point the same
+/// way, which is enough to exercise search without an embedding API. Text with no
+/// words gets the first basis vector, since Qdrant can't score a zero vector.
+pub fn hash_embedding(text: &str, dimension: usize) -> Vec<f32> {
+    let mut vector = vec![0.0f32; dimension];
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        let hash = digest(&SHA256, word.to_lowercase().as_bytes());
+        let bytes: [u8; 8] = hash.as_ref()[..8].try_into().expect("sha256 has 32 bytes");
+        let bits = u64::from_le_bytes(bytes);
+        let sign = if bits >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[(bits % dimension as u64) as usize] += sign;
+    }
+    
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        vector[0] = 1.0;
+    } else {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Sort an OpenAI client error into rate-limit, auth and server failures so callers
+/// can decide whether to back off, give up or retry; anything else stays in the
+/// generic `OpenAI` variant
+fn classify_error(error: OpenAIError) -> UnifiedRagError {
+    match &error {
+        OpenAIError::ApiError(api) => {
+            let code = api.code.as_deref().unwrap_or_default();
+            let kind = api.r#type.as_deref().unwrap_or_default();
+            match (code, kind) {
+                ("rate_limit_exceeded", _) | (_, "requests" | "tokens") => {
+                    UnifiedRagError::OpenAIRateLimit(api.message.clone())
+                }
+                ("invalid_api_key" | "invalid_organization", _)
+                | (_, "authentication_error" | "permission_error") => {
+                    UnifiedRagError::OpenAIAuth(api.message.clone())
+                }
+                (_, "server_error" | "service_unavailable") => UnifiedRagError::OpenAIServer(api.message.clone()),
+                _ => UnifiedRagError::OpenAI(error),
+            }
+        }
+        OpenAIError::Reqwest(e) => match e.status().map(|s| s.as_u16()) {
+            Some(429) => UnifiedRagError::OpenAIRateLimit(e.to_string()),
+            Some(401 | 403) => UnifiedRagError::OpenAIAuth(e.to_string()),
+            Some(500..=599) => UnifiedRagError::OpenAIServer(e.to_string()),
+            None if e.is_timeout() || e.is_connect() => UnifiedRagError::OpenAIServer(e.to_string()),
+            _ => UnifiedRagError::OpenAI(error),
+        },
+        _ => UnifiedRagError::OpenAI(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+    
+    #[test]
+    fn hash_embeddings_are_deterministic_unit_vectors() {
+        let a = hash_embedding("Redis caches the hot memories", 64);
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, hash_embedding("redis CACHES the hot memories!", 64));
+        assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
         
-        Ok(embeddings)
+        let empty = hash_embedding("  ...  ", 8);
+        assert_eq!(empty[0], 1.0);
+        assert!(empty[1..].iter().all(|&v| v == 0.0));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn hash_embeddings_of_texts_sharing_words_are_closer() {
+        let query = hash_embedding("qdrant vector search", 256);
+        let related = hash_embedding("semantic search runs in qdrant", 256);
+        let unrelated = hash_embedding("the cat sat on a mat", 256);
+        assert!(cosine(&query, &related) > cosine(&query, &unrelated));
+    }
+}
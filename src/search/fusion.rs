@@ -0,0 +1,78 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::Memory;
+use crate::text;
+
+/// Result order: highest `relevance_score` first, ties broken by newest `created_at`
+/// and then by `id`, so equal scores always come back in the same order.
+pub fn rank_order(a: &Memory, b: &Memory) -> Ordering {
+    b.relevance_score
+        .total_cmp(&a.relevance_score)
+        .then_with(|| b.created_at.cmp(&a.created_at))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Merge cache and Qdrant results into one ranked list.
+///
+/// Each memory gets `alpha * semantic + (1 - alpha) * cache`, where `semantic` is
+/// the Qdrant score and `cache` is the keyword overlap between the query and the
+/// content of a cache hit. A memory missing from one source scores 0 there.
+/// The fused value replaces `relevance_score`.
+pub fn fuse(
+    query: &str,
+    cache_results: Vec<Memory>,
+    semantic_results: Vec<Memory>,
+    alpha: f32,
+    limit: usize,
+) -> Vec<Memory> {
+    let mut merged: HashMap<Uuid, (Memory, f32, f32)> = HashMap::new();
+    
+    for memory in semantic_results {
+        let score = memory.relevance_score;
+        merged.insert(memory.id, (memory, score, 0.0));
+    }
+    
+    for memory in cache_results {
+        let cache_score = text::keyword_overlap(query, &memory.content);
+        merged
+            .entry(memory.id)
+            .and_modify(|entry| entry.2 = cache_score)
+            .or_insert((memory, 0.0, cache_score));
+    }
+    
+    let mut fused: Vec<Memory> = merged
+        .into_values()
+        .map(|(mut memory, semantic, cache)| {
+            memory.relevance_score = alpha * semantic + (1.0 - alpha) * cache;
+            memory
+        })
+        .collect();
+    
+    fused.sort_by(rank_order);
+    fused.truncate(limit);
+    fused
+}
+
+/// Drop memories whose normalized content repeats an earlier one, keeping the first
+/// (highest-ranked) copy. Used when results span instances that stored the same text.
+pub fn dedup_by_content(memories: Vec<Memory>) -> Vec<Memory> {
+    let mut seen = std::collections::HashSet::new();
+    memories
+        .into_iter()
+        .filter(|m| seen.insert(text::content_hash(&m.content)))
+        .collect()
+}
+
+/// Down-weight older memories: each score is multiplied by
+/// `0.5 ^ (age_days / half_life_days)`, with age measured from `created_at`
+/// (future timestamps count as age 0), then the list is re-sorted.
+pub fn apply_recency_decay(memories: &mut [Memory], half_life_days: f64, now: DateTime<Utc>) {
+    for memory in memories.iter_mut() {
+        let age_days = (now - memory.created_at).num_seconds().max(0) as f64 / 86_400.0;
+        memory.relevance_score *= 0.5f64.powf(age_days / half_life_days) as f32;
+    }
+    memories.sort_by(rank_order);
+}
@@ -1,14 +1,24 @@
 pub mod qdrant_search;
 pub mod embeddings;
+pub mod fusion;
+pub mod rerank;
 
 use crate::error::Result;
-use crate::models::{Memory, SearchRequest, SearchResult};
+use crate::models::{Memory, MemoryFilter};
 use async_trait::async_trait;
 
 #[async_trait]
 pub trait SearchLayer {
-    async fn search(&self, request: &SearchRequest) -> Result<SearchResult>;
     async fn index(&self, memory: &Memory) -> Result<()>;
+    /// Index many memories with one upsert per `QDRANT_UPSERT_BATCH_SIZE` points.
+    /// Stops at the first failure; chunks sent before it stay indexed.
+    async fn index_many(&self, memories: &[Memory]) -> Result<()>;
     async fn delete(&self, id: &str) -> Result<()>;
+    /// Delete every memory matching `filter` in one request, returning how many were
+    /// removed. An empty filter is rejected rather than deleting everything.
+    async fn delete_by_filter(&self, filter: &MemoryFilter) -> Result<u64>;
     async fn update_embedding(&self, id: &str, embedding: Vec<f32>) -> Result<()>;
+    /// Overwrite the given top-level payload fields in place, keeping vectors and
+    /// all other fields
+    async fn update_payload(&self, id: &str, payload: serde_json::Value) -> Result<()>;
 }
\ No newline at end of file
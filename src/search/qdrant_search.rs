@@ -1,31 +1,152 @@
 use std::sync::Arc;
 use async_trait::async_trait;
-use qdrant_client::Qdrant;
+use qdrant_client::{Qdrant, QdrantError};
+use chrono::DateTime;
 use qdrant_client::qdrant::{
-    CreateCollectionBuilder, Distance, VectorParamsBuilder,
+    CreateCollectionBuilder, Distance, VectorParamsBuilder, VectorsConfigBuilder,
     PointStruct, SearchPointsBuilder, DeletePointsBuilder,
     Filter, Condition, UpsertPointsBuilder, GetPointsBuilder,
-    PointId,
+    PointId, PointVectors, PointsIdsList, ScrollPointsBuilder, SetPayloadPointsBuilder, CountPointsBuilder,
+    RetrievedPoint, UpdatePointVectorsBuilder, CollectionStatus, PayloadSchemaType, HnswConfigDiffBuilder, SearchParamsBuilder,
+    CreateAliasBuilder, SnapshotDescription, CreateFieldIndexCollectionBuilder, FieldType, TextIndexParamsBuilder, TokenizerType,
+    point_id, vector_output, vectors_config,
 };
+use futures::stream::{self, Stream, TryStreamExt};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use qdrant_client::Payload;
 use uuid::Uuid;
 
 use crate::search::{SearchLayer, embeddings::EmbeddingGenerator};
+use crate::config::HnswConfig;
 use crate::error::{Result, UnifiedRagError};
-use crate::models::{Memory, SearchRequest, SearchResult};
+use crate::models::{
+    AggregateBucket, AggregateField, AggregateRequest, AggregateResult, CategoryMatchMode, CollectionInfoResult, VectorInfo,
+    Memory, MemoryFilter, MemoryMetadata, SearchRequest, SearchResult, SnapshotInfo, SnapshotResult, SnapshotsResult, VectorName,
+    category_prefixes,
+};
+
+/// `Memory` fields every payload keeps: those deserialization requires, plus the
+/// ones filters, scrolls and payload updates read
+const REQUIRED_PAYLOAD_FIELDS: [&str; 6] = ["id", "instance_id", "content", "metadata", "created_at", "updated_at"];
+
+/// Payload field `text_match` searches, under a full-text index
+pub const TEXT_INDEX_FIELD: &str = "content";
+
+/// Payload fields that `metadata_conditions` filters on
+const FILTER_FIELDS: [&str; 4] = ["instance_id", "metadata.category", "metadata.category_path", "metadata.tags"];
+
+/// Points per scroll request
+const SCROLL_PAGE_SIZE: u32 = 256;
 
 #[derive(Clone)]
 pub struct QdrantSearch {
     client: Arc<Qdrant>,
     collection_name: String,
     embedding_generator: Arc<EmbeddingGenerator>,
+    vector_size: u64,
+    /// False for collections created before named vectors, which hold a single unnamed vector
+    named_vectors: bool,
+    /// Leave content, summary and embedding out of payloads (`STORAGE_MODE=redis_primary`)
+    minimal_payload: bool,
+    /// `collection_name` is a collection alias rather than a physical collection
+    aliased: bool,
+    /// Metric of the content vector, which decides how raw scores are normalized
+    distance: Distance,
+    /// Most points `index_many` sends in one upsert request
+    upsert_batch_size: usize,
+    /// Optional `Memory` fields kept in payloads besides `REQUIRED_PAYLOAD_FIELDS`
+    payload_fields: Vec<String>,
+}
+
+/// gRPC status codes Qdrant answers with when snapshots aren't allowed on the
+/// instance: permission denied (read-only key), failed precondition, unimplemented
+const SNAPSHOTS_UNAVAILABLE_CODES: [i32; 3] = [7, 9, 12];
+
+/// A raw Qdrant score as relevance in [0, 1], equal to the cosine similarity (negatives
+/// clamped to 0) whenever the vectors are unit length, as OpenAI embeddings are:
+/// - Cosine: the similarity itself
+/// - Dot: the product, which is the cosine for unit vectors
+/// - Euclid: `1 - d²/2`, since `d² = 2 - 2·cos` for unit vectors
+/// - Manhattan: `1 / (1 + d)`, which has no cosine equivalent
+pub fn normalized_score(distance: Distance, score: f32) -> f32 {
+    let relevance = match distance {
+        Distance::Euclid => 1.0 - score * score / 2.0,
+        Distance::Manhattan => 1.0 / (1.0 + score.max(0.0)),
+        _ => score,
+    };
+    relevance.clamp(0.0, 1.0)
+}
+
+/// The raw score Qdrant should cut at for a normalized `threshold`: a minimum
+/// similarity for cosine/dot, a maximum distance for Euclid/Manhattan. `None` when
+/// every result passes.
+fn raw_score_threshold(distance: Distance, threshold: f32) -> Option<f32> {
+    if threshold <= 0.0 {
+        return None;
+    }
+    let threshold = threshold.min(1.0);
+    Some(match distance {
+        Distance::Euclid => (2.0 * (1.0 - threshold)).sqrt(),
+        Distance::Manhattan => 1.0 / threshold - 1.0,
+        _ => threshold,
+    })
+}
+
+/// Physical collection created behind the alias `alias` on first startup
+pub fn initial_collection_name(alias: &str) -> String {
+    format!("{}_v1", alias)
+}
+
+/// The collection `alias` currently points at, if it is an alias
+pub async fn alias_target(client: &Qdrant, alias: &str) -> Result<Option<String>> {
+    let aliases = client
+        .list_aliases()
+        .await
+        .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+    Ok(aliases.aliases
+        .into_iter()
+        .find(|a| a.alias_name == alias)
+        .map(|a| a.collection_name))
+}
+
+/// Poll Qdrant's health check until it answers, backing off from 500ms up to 10s
+/// between attempts. Covers containers that start before Qdrant is listening.
+pub async fn wait_until_ready(client: &Qdrant, max_attempts: u32) -> Result<()> {
+    let mut delay = std::time::Duration::from_millis(500);
+    let mut attempt = 1;
+    
+    loop {
+        match client.health_check().await {
+            Ok(reply) => {
+                tracing::info!("Qdrant is ready (version {})", reply.version);
+                return Ok(());
+            }
+            Err(e) if attempt >= max_attempts => {
+                return Err(UnifiedRagError::Qdrant(format!(
+                    "Qdrant not reachable after {} attempts: {}", attempt, e
+                )));
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Qdrant not ready (attempt {}/{}): {}. Retrying in {:?}",
+                    attempt, max_attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(std::time::Duration::from_secs(10));
+                attempt += 1;
+            }
+        }
+    }
 }
 
 impl QdrantSearch {
     pub async fn new(
         client: Arc<Qdrant>, 
         collection_name: String,
-        embedding_generator: Arc<EmbeddingGenerator>
+        embedding_generator: Arc<EmbeddingGenerator>,
+        vector_size: u64,
+        hnsw: &HnswConfig,
+        auto_create: bool,
     ) -> Result<Self> {
         // Try to list collections with better error handling
         let collections = match client.list_collections().await {
@@ -43,14 +164,38 @@ impl QdrantSearch {
             .iter()
             .any(|c| c.name == collection_name);
         
-        if !collection_exists {
-            // Create collection with vector configuration
-            match client.create_collection(
-                CreateCollectionBuilder::new(&collection_name)
-                    .vectors_config(VectorParamsBuilder::new(1536, Distance::Cosine))
-            ).await {
+        if !collection_exists && !auto_create {
+            return Err(UnifiedRagError::Configuration(format!(
+                "Qdrant collection '{}' does not exist and QDRANT_AUTO_CREATE is off; check QDRANT_COLLECTION or create it first",
+                collection_name
+            )));
+        }
+        
+        let (named_vectors, distance) = if !collection_exists {
+            // Create collection with a named vector per embedded field
+            let mut vectors = VectorsConfigBuilder::default();
+            for name in [VectorName::Content, VectorName::Summary] {
+                vectors.add_named_vector_params(
+                    name.as_str(),
+                    VectorParamsBuilder::new(vector_size, Distance::Cosine),
+                );
+            }
+            
+            let mut create = CreateCollectionBuilder::new(&collection_name).vectors_config(vectors);
+            if hnsw.m.is_some() || hnsw.ef_construct.is_some() {
+                let mut hnsw_config = HnswConfigDiffBuilder::default();
+                if let Some(m) = hnsw.m {
+                    hnsw_config = hnsw_config.m(m);
+                }
+                if let Some(ef_construct) = hnsw.ef_construct {
+                    hnsw_config = hnsw_config.ef_construct(ef_construct);
+                }
+                create = create.hnsw_config(hnsw_config);
+            }
+            
+            match client.create_collection(create).await {
                 Ok(_) => {
-                    tracing::info!("Created Qdrant collection: {}", collection_name);
+                    tracing::info!("Created Qdrant collection: {} ({} dimensions)", collection_name, vector_size);
                 }
                 Err(e) => {
                     tracing::error!("Failed to create Qdrant collection '{}': {}", collection_name, e);
@@ -59,27 +204,357 @@ impl QdrantSearch {
                     )));
                 }
             }
+            (true, Distance::Cosine)
         } else {
             tracing::info!("Using existing Qdrant collection: {}", collection_name);
-        }
+            if hnsw.m.is_some() || hnsw.ef_construct.is_some() {
+                tracing::warn!(
+                    "QDRANT_HNSW_M/QDRANT_HNSW_EF_CONSTRUCT only apply when creating a collection; '{}' keeps its index settings",
+                    collection_name
+                );
+            }
+            let info = client
+                .collection_info(&collection_name)
+                .await
+                .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+            let vectors = info.result
+                .and_then(|info| info.config)
+                .and_then(|config| config.params)
+                .and_then(|params| params.vectors_config)
+                .and_then(|vectors| vectors.config);
+            let (existing_size, distance, named_vectors) = match vectors {
+                Some(vectors_config::Config::Params(params)) => (Some(params.size), Some(params.distance), false),
+                Some(vectors_config::Config::ParamsMap(map)) => {
+                    let content = map.map.get(VectorName::Content.as_str()).ok_or_else(|| {
+                        UnifiedRagError::Configuration(format!(
+                            "Collection '{}' has named vectors but no '{}' vector",
+                            collection_name, VectorName::Content.as_str()
+                        ))
+                    })?;
+                    (Some(content.size), Some(content.distance), true)
+                }
+                None => (None, None, false),
+            };
+            let distance = distance.and_then(|d| Distance::try_from(d).ok()).unwrap_or(Distance::Cosine);
+            if distance != Distance::Cosine {
+                tracing::info!(
+                    "Collection '{}' uses {} distance; scores are normalized to cosine-equivalent relevance",
+                    collection_name, distance.as_str_name()
+                );
+            }
+            if !named_vectors {
+                tracing::info!("Collection '{}' uses a single unnamed vector; summary search is unavailable", collection_name);
+            }
+            
+            match existing_size {
+                Some(size) if size != vector_size => {
+                    return Err(UnifiedRagError::Configuration(format!(
+                        "Collection '{}' stores {}-dimensional vectors but the embedding model produces {}",
+                        collection_name, size, vector_size
+                    )));
+                }
+                Some(_) => {}
+                None => tracing::warn!(
+                    "Could not read the vector size of '{}', assuming {}",
+                    collection_name, vector_size
+                ),
+            }
+            (named_vectors, distance)
+        };
         
         Ok(Self {
             client,
             collection_name,
             embedding_generator,
+            vector_size,
+            named_vectors,
+            minimal_payload: false,
+            aliased: false,
+            distance,
+            upsert_batch_size: 256,
+            payload_fields: ["summary", "access_count", "relevance_score"].map(String::from).to_vec(),
         })
     }
-}
-
-#[async_trait]
-impl SearchLayer for QdrantSearch {
-    async fn search(&self, request: &SearchRequest) -> Result<SearchResult> {
-        let start_time = std::time::Instant::now();
+    
+    /// Open the physical collection behind the alias `alias` and operate through the
+    /// alias, so the collection can be swapped (`point_alias`) without reconfiguring.
+    /// On first startup `<alias>_v1` is created and the alias pointed at it, unless
+    /// `auto_create` is off. A collection already named `alias` predates aliases and
+    /// is used directly, since an alias can't share a collection's name.
+    pub async fn with_alias(
+        client: Arc<Qdrant>,
+        alias: String,
+        embedding_generator: Arc<EmbeddingGenerator>,
+        vector_size: u64,
+        hnsw: &HnswConfig,
+        auto_create: bool,
+    ) -> Result<Self> {
+        let existing = alias_target(&client, &alias).await?;
+        let physical = match existing {
+            Some(ref collection) => collection.clone(),
+            None => {
+                let legacy = client
+                    .collection_exists(&alias)
+                    .await
+                    .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+                if legacy {
+                    tracing::warn!(
+                        "'{}' is a collection, not an alias; using it directly. Migrate it with rag_migrate_model to enable alias swaps",
+                        alias
+                    );
+                    return Self::new(client, alias, embedding_generator, vector_size, hnsw, auto_create).await;
+                }
+                let initial = initial_collection_name(&alias);
+                // Name the configured alias, not the derived collection, when nothing is there
+                let initial_exists = client
+                    .collection_exists(&initial)
+                    .await
+                    .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+                if !initial_exists && !auto_create {
+                    return Err(UnifiedRagError::Configuration(format!(
+                        "Neither an alias nor a collection named '{}' exists in Qdrant and QDRANT_AUTO_CREATE is off; check QDRANT_COLLECTION",
+                        alias
+                    )));
+                }
+                initial
+            }
+        };
+        
+        let mut search = Self::new(client, physical.clone(), embedding_generator, vector_size, hnsw, auto_create).await?;
+        if existing.is_none() {
+            search.point_alias(&alias).await?;
+            tracing::info!("Created Qdrant alias {} -> {}", alias, physical);
+        } else {
+            tracing::info!("Qdrant alias {} points at {}", alias, physical);
+        }
+        search.collection_name = alias;
+        search.aliased = true;
+        Ok(search)
+    }
+    
+    /// Create the word-tokenized, lowercased full-text index on `content` that
+    /// `text_match` relies on, unless the collection already has an index there.
+    /// Returns whether an index was created.
+    pub async fn ensure_text_index(&self) -> Result<bool> {
+        let collection = self.physical_collection().await?;
+        let info = self.client
+            .collection_info(&collection)
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        if info.result.is_some_and(|info| info.payload_schema.contains_key(TEXT_INDEX_FIELD)) {
+            return Ok(false);
+        }
+        
+        let params = TextIndexParamsBuilder::new(TokenizerType::Word).lowercase(true);
+        self.client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(&collection, TEXT_INDEX_FIELD, FieldType::Text)
+                    .field_index_params(params)
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        Ok(true)
+    }
+    
+    /// The physical collection behind this search layer: the alias target when
+    /// operating through an alias, as of now rather than startup
+    pub async fn physical_collection(&self) -> Result<String> {
+        if !self.aliased {
+            return Ok(self.collection_name.clone());
+        }
+        alias_target(&self.client, &self.collection_name).await?.ok_or_else(|| {
+            UnifiedRagError::Qdrant(format!("Qdrant alias '{}' no longer exists", self.collection_name))
+        })
+    }
+    
+    /// Index only vectors and filter metadata, leaving the full memory to Redis
+    pub fn with_minimal_payload(mut self, minimal: bool) -> Self {
+        self.minimal_payload = minimal;
+        self
+    }
+    
+    /// Keep only these optional `Memory` fields in point payloads
+    pub fn with_payload_fields(mut self, fields: Vec<String>) -> Self {
+        self.payload_fields = fields;
+        self
+    }
+    
+    /// Split `index_many` into upserts of at most `size` points
+    pub fn with_upsert_batch_size(mut self, size: usize) -> Self {
+        self.upsert_batch_size = size.max(1);
+        self
+    }
+    
+    /// Build a memory's point, embedding its content (when it carries no embedding)
+    /// and its summary, and checking both against the collection's dimension
+    async fn point(&self, memory: &Memory) -> Result<PointStruct> {
+        // Generate embedding if not present
+        let embedding = match &memory.embedding {
+            Some(e) => e.clone(),
+            None => self.embedding_generator
+                .generate_embedding(&memory.content)
+                .await?
+        };
+        
+        // Always EMBEDDING_MODEL; rag_store rejects a summary with any other `model`
+        let summary_embedding = match (&memory.summary, self.named_vectors) {
+            (Some(summary), true) => Some(self.embedding_generator.generate_embedding(summary).await?),
+            _ => None,
+        };
+        self.check_dimension(&embedding, memory.id)?;
+        if let Some(ref summary_embedding) = summary_embedding {
+            self.check_dimension(summary_embedding, memory.id)?;
+        }
+        
+        // Create payload from memory, with category ancestors for prefix filtering.
+        // The vector lives in the point itself, so `embedding` only stays when configured.
+        let mut payload_json = serde_json::to_value(memory)?;
+        payload_json["metadata"] = metadata_payload(&memory.metadata)?;
+        if let Some(fields) = payload_json.as_object_mut() {
+            fields.retain(|name, _| {
+                REQUIRED_PAYLOAD_FIELDS.contains(&name.as_str()) || self.payload_fields.iter().any(|f| f == name)
+            });
+        }
+        if self.minimal_payload {
+            // Keep the payload deserializable as a Memory; callers hydrate from Redis
+            payload_json["content"] = serde_json::json!("");
+            if let Some(fields) = payload_json.as_object_mut() {
+                fields.remove("summary");
+                fields.remove("embedding");
+            }
+        }
+        let payload: Payload = serde_json::from_value(payload_json)?;
+        
+        // Create point for Qdrant
+        let point = if self.named_vectors {
+            let mut vectors = HashMap::from([(VectorName::Content.as_str().to_string(), embedding)]);
+            if let Some(summary_embedding) = summary_embedding {
+                vectors.insert(VectorName::Summary.as_str().to_string(), summary_embedding);
+            }
+            PointStruct::new(memory.id.to_string(), vectors, payload)
+        } else {
+            if summary_embedding.is_some() {
+                tracing::warn!(
+                    "Collection '{}' has no summary vector; indexing memory {} by content only",
+                    self.collection_name, memory.id
+                );
+            }
+            PointStruct::new(memory.id.to_string(), embedding, payload)
+        };
+        Ok(point)
+    }
+    
+    /// Replace a point's `metadata` payload (and `updated_at`) after a metadata-only edit
+    pub async fn update_metadata(
+        &self,
+        id: &str,
+        metadata: &MemoryMetadata,
+        updated_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let payload = serde_json::json!({
+            "metadata": metadata_payload(metadata)?,
+            "updated_at": updated_at,
+        });
+        self.update_payload(id, payload).await
+    }
+    
+    /// Vector config, point counts, optimizer and payload index status of the collection
+    pub async fn collection_info(&self) -> Result<CollectionInfoResult> {
+        let collection = self.physical_collection().await?;
+        let info = self.client
+            .collection_info(&collection)
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?
+            .result
+            .ok_or_else(|| UnifiedRagError::Qdrant(format!("No info returned for collection '{}'", collection)))?;
         
-        // Generate embedding for query
-        let query_embedding = self.embedding_generator
-            .generate_embedding(&request.query)
-            .await?;
+        let distance_name = |distance: i32| {
+            Distance::try_from(distance).map_or_else(|_| distance.to_string(), |d| d.as_str_name().to_string())
+        };
+        let vectors = match info.config
+            .as_ref()
+            .and_then(|config| config.params.as_ref())
+            .and_then(|params| params.vectors_config.as_ref())
+            .and_then(|vectors| vectors.config.as_ref())
+        {
+            Some(vectors_config::Config::Params(params)) => vec![VectorInfo {
+                name: String::new(),
+                size: params.size,
+                distance: distance_name(params.distance),
+            }],
+            Some(vectors_config::Config::ParamsMap(map)) => {
+                let mut vectors: Vec<VectorInfo> = map.map
+                    .iter()
+                    .map(|(name, params)| VectorInfo {
+                        name: name.clone(),
+                        size: params.size,
+                        distance: distance_name(params.distance),
+                    })
+                    .collect();
+                vectors.sort_by(|a, b| a.name.cmp(&b.name));
+                vectors
+            }
+            None => Vec::new(),
+        };
+        
+        let payload_indexes: BTreeMap<String, String> = info.payload_schema
+            .iter()
+            .map(|(field, schema)| {
+                let kind = PayloadSchemaType::try_from(schema.data_type)
+                    .map_or_else(|_| schema.data_type.to_string(), |t| t.as_str_name().to_string());
+                (field.clone(), kind)
+            })
+            .collect();
+        let filter_fields_indexed = FILTER_FIELDS
+            .iter()
+            .map(|field| (field.to_string(), payload_indexes.contains_key(*field)))
+            .collect();
+        
+        let status = CollectionStatus::try_from(info.status)
+            .map_or_else(|_| info.status.to_string(), |s| s.as_str_name().to_ascii_lowercase());
+        let optimizer_error = info.optimizer_status
+            .as_ref()
+            .map(|o| o.error.clone())
+            .filter(|e| !e.is_empty());
+        
+        Ok(CollectionInfoResult {
+            alias: self.aliased.then(|| self.collection_name.clone()),
+            collection,
+            status,
+            optimizer_ok: info.optimizer_status.as_ref().is_none_or(|o| o.ok),
+            optimizer_error,
+            points_count: info.points_count,
+            indexed_vectors_count: info.indexed_vectors_count,
+            segments_count: info.segments_count,
+            vectors,
+            payload_indexes,
+            filter_fields_indexed,
+        })
+    }
+    
+    /// Reject a vector the collection can't hold before Qdrant does
+    fn check_dimension(&self, embedding: &[f32], id: impl std::fmt::Display) -> Result<()> {
+        check_dimension(&self.collection_name, self.vector_size, embedding, id)
+    }
+    
+    /// Dimension of the collection's vectors
+    pub fn vector_size(&self) -> u64 {
+        self.vector_size
+    }
+    
+    /// Whether the collection stores named `content`/`summary` vectors
+    pub fn has_named_vectors(&self) -> bool {
+        self.named_vectors
+    }
+    
+    /// Run the Qdrant query for an already-computed query embedding
+    pub async fn search_with_embedding(
+        &self,
+        request: &SearchRequest,
+        query_embedding: Vec<f32>,
+    ) -> Result<SearchResult> {
+        let start_time = std::time::Instant::now();
         
         // Build search query
         let mut search_builder = SearchPointsBuilder::new(
@@ -89,21 +564,48 @@ impl SearchLayer for QdrantSearch {
         )
         .with_payload(true);
         
+        if self.named_vectors {
+            search_builder = search_builder.vector_name(request.vector.as_str());
+        } else if request.vector != VectorName::Content {
+            return Err(UnifiedRagError::SearchError(format!(
+                "Collection '{}' has no '{}' vector", self.collection_name, request.vector.as_str()
+            )));
+        }
+        
         // Add filters if specified
-        let mut filter_conditions = vec![];
+        let mut filter_conditions = metadata_conditions(
+            request.category_filter.as_deref(),
+            request.category_match_mode,
+            request.tags_filter.as_deref(),
+            request.tag_min_match,
+            request.instance_filter.as_deref(),
+        );
+        if let Some(ref chain_id) = request.chain_filter {
+            filter_conditions.push(Condition::matches("metadata.chain_id", chain_id.clone()));
+        }
+        if let Some(ref extra) = request.extra_filters {
+            filter_conditions.extend(extra_conditions(extra));
+        }
         
-        if let Some(ref category) = request.category_filter {
-            filter_conditions.push(Condition::matches("metadata.category", category.clone()));
+        if let Some(ref text) = request.text_match {
+            filter_conditions.push(Condition::matches_text(TEXT_INDEX_FIELD, text.clone()));
         }
         
-        if let Some(ref tags_filter) = request.tags_filter {
-            for tag in tags_filter {
-                filter_conditions.push(Condition::matches("metadata.tags", tag.clone()));
-            }
+        let mut filter = Filter::must(filter_conditions);
+        if !request.exclude_ids.is_empty() {
+            filter.must_not.push(Condition::has_id(request.exclude_ids.iter().map(|id| id.to_string())));
+        }
+        if !filter.must.is_empty() || !filter.must_not.is_empty() {
+            search_builder = search_builder.filter(filter);
+        }
+        
+        if let Some(ef) = request.hnsw_ef {
+            search_builder = search_builder.params(SearchParamsBuilder::default().hnsw_ef(ef));
         }
         
-        if !filter_conditions.is_empty() {
-            search_builder = search_builder.filter(Filter::must(filter_conditions));
+        // `threshold` is on the normalized scale; Qdrant cuts on raw scores
+        if let Some(raw) = request.threshold.and_then(|t| raw_score_threshold(self.distance, t)) {
+            search_builder = search_builder.score_threshold(raw);
         }
         
         // Execute search
@@ -112,13 +614,17 @@ impl SearchLayer for QdrantSearch {
             .await
             .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
         
-        // Convert results to Memory objects
+        // Convert results to Memory objects; one bad legacy payload shouldn't fail the query
         let mut memories = Vec::new();
         for point in search_results.result {
-            // Deserialize payload to Memory
-            let memory_json = serde_json::to_value(&point.payload)?;
-            let memory: Memory = serde_json::from_value(memory_json)?;
-            memories.push(memory);
+            let memory = serde_json::to_value(&point.payload).and_then(serde_json::from_value::<Memory>);
+            match memory {
+                Ok(mut memory) => {
+                    memory.relevance_score = normalized_score(self.distance, point.score);
+                    memories.push(memory);
+                }
+                Err(e) => tracing::warn!("Skipping undeserializable Qdrant payload: {}", e),
+            }
         }
         
         let search_time_ms = start_time.elapsed().as_millis() as u64;
@@ -132,30 +638,550 @@ impl SearchLayer for QdrantSearch {
             cache_hits: 0, // Qdrant doesn't track cache hits
             total_results,
             search_time_ms,
+            timings: None,
+            partial: false,
+            warnings: Vec::new(),
+            truncated: false,
+            response_truncated: false,
+            next_offset: None,
+        })
+    }
+
+    /// Tally memories per group by scrolling the collection.
+    ///
+    /// Category/tag/instance filters are pushed down to Qdrant; the created_at
+    /// window is applied while tallying since timestamps are stored as strings.
+    pub async fn aggregate(&self, request: &AggregateRequest) -> Result<AggregateResult> {
+        let filter_conditions = metadata_conditions(
+            request.category_filter.as_deref(),
+            CategoryMatchMode::Exact,
+            request.tags_filter.as_deref(),
+            None,
+            request.instance_filter.as_deref(),
+        );
+        
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let mut total_memories = 0u64;
+        
+        for payload in self.scroll_payloads(&filter_conditions).await? {
+            if request.created_after.is_some() || request.created_before.is_some() {
+                let created_at = payload["created_at"]
+                    .as_str()
+                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                    .map(|d| d.with_timezone(&chrono::Utc));
+                let Some(created_at) = created_at else { continue };
+                if request.created_after.is_some_and(|after| created_at < after)
+                    || request.created_before.is_some_and(|before| created_at >= before)
+                {
+                    continue;
+                }
+            }
+            
+            total_memories += 1;
+            
+            let metadata = &payload["metadata"];
+            let keys: Vec<String> = match request.group_by {
+                AggregateField::Tag => metadata["tags"]
+                    .as_array()
+                    .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                    .unwrap_or_default(),
+                AggregateField::Category => metadata["category"].as_str().map(String::from).into_iter().collect(),
+                AggregateField::Framework => metadata["framework"].as_str().map(String::from).into_iter().collect(),
+                AggregateField::Source => metadata["source"].as_str().map(String::from).into_iter().collect(),
+            };
+            
+            if keys.is_empty() {
+                *counts.entry("(none)".to_string()).or_default() += 1;
+            }
+            for key in keys {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        
+        let mut groups: Vec<AggregateBucket> = counts
+            .into_iter()
+            .map(|(key, count)| AggregateBucket { key, count })
+            .collect();
+        groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        
+        Ok(AggregateResult {
+            group_by: request.group_by,
+            groups,
+            total_memories,
         })
     }
     
-    async fn index(&self, memory: &Memory) -> Result<()> {
-        // Generate embedding if not present
-        let embedding = match &memory.embedding {
-            Some(e) => e.clone(),
-            None => self.embedding_generator
-                .generate_embedding(&memory.content)
-                .await?
+    /// Delete every point owned by `instance_id`, returning how many were removed
+    pub async fn delete_instance(&self, instance_id: &str) -> Result<u64> {
+        let filter = MemoryFilter {
+            instance_filter: Some(vec![instance_id.to_string()]),
+            ..MemoryFilter::default()
         };
+        self.delete_by_filter(&filter).await
+    }
+    
+    /// Ids of every point matching `filter`, paged through with the scroll cursor
+    pub async fn matching_ids(&self, filter: &MemoryFilter) -> Result<Vec<String>> {
+        self.ids_where(&filter_conditions(filter)).await
+    }
+    
+    /// Ids of `instance`'s points on the chain `chain_id`
+    pub async fn chain_ids(&self, instance: &str, chain_id: &str) -> Result<Vec<String>> {
+        self.ids_where(&[
+            Condition::matches("instance_id", instance.to_string()),
+            Condition::matches("metadata.chain_id", chain_id.to_string()),
+        ])
+        .await
+    }
+    
+    async fn ids_where(&self, conditions: &[Condition]) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut offset: Option<PointId> = None;
         
-        // Create payload from memory
-        let payload_json = serde_json::to_value(memory)?;
-        let payload: Payload = serde_json::from_value(payload_json)?;
+        loop {
+            let (points, next) = self.scroll_page(conditions, offset.take(), false).await?;
+            ids.extend(points.into_iter().filter_map(|point| match point.id?.point_id_options? {
+                point_id::PointIdOptions::Uuid(id) => Some(id),
+                point_id::PointIdOptions::Num(id) => Some(id.to_string()),
+            }));
+            
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
         
-        // Create point for Qdrant
-        let point = PointStruct::new(
-            memory.id.to_string(),
-            embedding,
-            payload
-        );
+        Ok(ids)
+    }
+    
+    /// Count the points matching `conditions`, then delete them all in one request
+    async fn delete_matching(&self, conditions: Vec<Condition>) -> Result<u64> {
+        let filter = Filter::must(conditions);
+        
+        let count = self.client
+            .count(
+                CountPointsBuilder::new(&self.collection_name)
+                    .filter(filter.clone())
+                    .exact(true)
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?
+            .result
+            .map(|r| r.count)
+            .unwrap_or(0);
         
-        // Upsert point
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.collection_name)
+                    .points(filter)
+                    .wait(true)
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        
+        Ok(count)
+    }
+    
+    /// Fetch one memory by id, with its content embedding
+    pub async fn get_memory(&self, id: &str) -> Result<Option<Memory>> {
+        Ok(self.get_memories(&[id.to_string()]).await?.into_iter().next())
+    }
+    
+    /// Fetch several memories in one request, filling `embedding` from the stored
+    /// content vector. Missing ids and undeserializable payloads are skipped; order
+    /// follows Qdrant's response.
+    pub async fn get_memories(&self, ids: &[String]) -> Result<Vec<Memory>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let point_ids: Vec<PointId> = ids.iter().map(|id| PointId::from(id.as_str())).collect();
+        let points = self.client
+            .get_points(
+                GetPointsBuilder::new(&self.collection_name, point_ids)
+                    .with_payload(true)
+                    .with_vectors(true)
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        
+        Ok(points.result
+            .iter()
+            .filter_map(|point| match self.point_memory(point) {
+                Ok(memory) => Some(memory),
+                Err(e) => {
+                    tracing::warn!("Skipping undeserializable Qdrant payload: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+    
+    /// Snapshot the physical collection on this Qdrant node
+    pub async fn create_snapshot(&self) -> Result<SnapshotResult> {
+        let collection = self.physical_collection().await?;
+        let response = self.client
+            .create_snapshot(collection.as_str())
+            .await
+            .map_err(|e| snapshot_error(&collection, e))?;
+        let description = response.snapshot_description.ok_or_else(|| {
+            UnifiedRagError::Qdrant(format!("Qdrant returned no snapshot description for '{}'", collection))
+        })?;
+        
+        Ok(SnapshotResult {
+            snapshot: snapshot_info(&collection, description),
+            collection,
+        })
+    }
+    
+    /// Snapshots of the physical collection stored on this Qdrant node, newest first
+    pub async fn list_snapshots(&self) -> Result<SnapshotsResult> {
+        let collection = self.physical_collection().await?;
+        let response = self.client
+            .list_snapshots(collection.as_str())
+            .await
+            .map_err(|e| snapshot_error(&collection, e))?;
+        
+        let mut snapshots: Vec<SnapshotInfo> = response.snapshot_descriptions
+            .into_iter()
+            .map(|description| snapshot_info(&collection, description))
+            .collect();
+        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.name.cmp(&a.name)));
+        
+        Ok(SnapshotsResult { collection, snapshots })
+    }
+    
+    /// Which of `ids` already have a point, without loading payloads or vectors
+    pub async fn existing_ids(&self, ids: &[String]) -> Result<HashSet<String>> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        
+        let point_ids: Vec<PointId> = ids.iter().map(|id| PointId::from(id.as_str())).collect();
+        let points = self.client
+            .get_points(
+                GetPointsBuilder::new(&self.collection_name, point_ids)
+                    .with_payload(false)
+                    .with_vectors(false)
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        
+        Ok(points.result
+            .into_iter()
+            .filter_map(|point| match point.id?.point_id_options? {
+                point_id::PointIdOptions::Uuid(id) => Some(id),
+                point_id::PointIdOptions::Num(id) => Some(id.to_string()),
+            })
+            .collect())
+    }
+    
+    /// Point the collection alias `alias` at this search layer's physical collection,
+    /// creating it if needed. Qdrant swaps an existing alias's target in one step;
+    /// a server that refuses to overwrite gets a delete and recreate instead, during
+    /// which readers may briefly see the alias missing.
+    pub async fn point_alias(&self, alias: &str) -> Result<()> {
+        let collection = self.physical_collection().await?;
+        let create = || self.client.create_alias(CreateAliasBuilder::new(&collection, alias));
+        
+        match create().await {
+            Ok(_) => Ok(()),
+            Err(e) => match alias_target(&self.client, alias).await? {
+                Some(ref current) if *current == collection => Ok(()),
+                Some(_) => {
+                    tracing::warn!("Qdrant refused to repoint alias {} ({}); deleting and recreating it", alias, e);
+                    self.client
+                        .delete_alias(alias)
+                        .await
+                        .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+                    create().await.map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+                    Ok(())
+                }
+                None => Err(UnifiedRagError::Qdrant(e.to_string())),
+            },
+        }
+    }
+    
+    /// Deserialize a point's payload, filling `embedding` from its content vector when returned
+    fn point_memory(&self, point: &RetrievedPoint) -> Result<Memory> {
+        let mut memory: Memory = serde_json::from_value(serde_json::to_value(&point.payload)?)?;
+        let vector = point.vectors.as_ref().and_then(|vectors| {
+            if self.named_vectors {
+                vectors.get_vector_by_name(VectorName::Content.as_str())
+            } else {
+                vectors.get_vector()
+            }
+        });
+        if let Some(vector_output::Vector::Dense(dense)) = vector {
+            memory.embedding = Some(dense.data);
+        }
+        Ok(memory)
+    }
+    
+    /// All memories matching the metadata filters; undeserializable payloads are skipped
+    pub async fn list_memories(
+        &self,
+        category: Option<&str>,
+        tags: Option<&[String]>,
+        instances: Option<&[String]>,
+    ) -> Result<Vec<Memory>> {
+        self.scroll_memories(category, tags, instances, false).try_concat().await
+    }
+    
+    /// Page through every memory matching the metadata filters with Qdrant's scroll
+    /// cursor, yielding one batch per page. Vectors are loaded into `embedding` only
+    /// when `with_vectors` is set. A missing collection yields nothing; payloads
+    /// that don't deserialize are skipped.
+    pub fn scroll_memories<'a>(
+        &'a self,
+        category: Option<&str>,
+        tags: Option<&[String]>,
+        instances: Option<&[String]>,
+        with_vectors: bool,
+    ) -> impl Stream<Item = Result<Vec<Memory>>> + 'a {
+        let conditions = metadata_conditions(category, CategoryMatchMode::Exact, tags, None, instances);
+        
+        stream::try_unfold(ScrollState::Start, move |state| {
+            let conditions = conditions.clone();
+            async move {
+                let offset = match state {
+                    ScrollState::Start => {
+                        if !self.collection_exists().await? {
+                            tracing::warn!("Qdrant collection {} not found; nothing to scroll", self.collection_name);
+                            return Ok(None);
+                        }
+                        None
+                    }
+                    ScrollState::Next(offset) => Some(offset),
+                    ScrollState::Done => return Ok(None),
+                };
+                
+                let (points, next) = self.scroll_page(&conditions, offset, with_vectors).await?;
+                let memories = points
+                    .iter()
+                    .filter_map(|point| match self.point_memory(point) {
+                        Ok(memory) => Some(memory),
+                        Err(e) => {
+                            tracing::warn!("Skipping undeserializable Qdrant payload: {}", e);
+                            None
+                        }
+                    })
+                    .collect();
+                let state = next.map_or(ScrollState::Done, ScrollState::Next);
+                Ok(Some((memories, state)))
+            }
+        })
+    }
+    
+    pub async fn collection_exists(&self) -> Result<bool> {
+        if self.aliased {
+            return Ok(alias_target(&self.client, &self.collection_name).await?.is_some());
+        }
+        self.client
+            .collection_exists(&self.collection_name)
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))
+    }
+    
+    /// One scroll request from `offset`, returning the points and the next page's offset
+    async fn scroll_page(
+        &self,
+        conditions: &[Condition],
+        offset: Option<PointId>,
+        with_vectors: bool,
+    ) -> Result<(Vec<RetrievedPoint>, Option<PointId>)> {
+        let mut scroll = ScrollPointsBuilder::new(&self.collection_name)
+            .limit(SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(with_vectors);
+        
+        if !conditions.is_empty() {
+            scroll = scroll.filter(Filter::must(conditions.to_vec()));
+        }
+        if let Some(id) = offset {
+            scroll = scroll.offset(id);
+        }
+        
+        let page = self.client
+            .scroll(scroll)
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        Ok((page.result, page.next_page_offset))
+    }
+    
+    /// Deserialize the payloads of up to `limit` points, returning how many were read
+    /// and the id and error of each one that isn't a valid memory
+    pub async fn sample_payload_errors(&self, limit: u32) -> Result<(usize, Vec<(String, String)>)> {
+        let scroll = ScrollPointsBuilder::new(&self.collection_name)
+            .limit(limit)
+            .with_payload(true)
+            .with_vectors(false);
+        let points = self.client
+            .scroll(scroll)
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?
+            .result;
+        
+        let errors = points
+            .iter()
+            .filter_map(|point| {
+                let error = self.point_memory(point).err()?;
+                let id = match point.id.clone().and_then(|id| id.point_id_options) {
+                    Some(point_id::PointIdOptions::Uuid(id)) => id,
+                    Some(point_id::PointIdOptions::Num(id)) => id.to_string(),
+                    None => "(no id)".to_string(),
+                };
+                Some((id, error.to_string()))
+            })
+            .collect();
+        Ok((points.len(), errors))
+    }
+    
+    /// Page through every point matching `conditions`, returning payloads as JSON
+    async fn scroll_payloads(&self, conditions: &[Condition]) -> Result<Vec<serde_json::Value>> {
+        let mut payloads = Vec::new();
+        let mut offset: Option<PointId> = None;
+        
+        loop {
+            let (points, next) = self.scroll_page(conditions, offset.take(), false).await?;
+            for point in points {
+                payloads.push(serde_json::to_value(&point.payload)?);
+            }
+            
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        
+        Ok(payloads)
+    }
+}
+
+fn snapshot_info(collection: &str, description: SnapshotDescription) -> SnapshotInfo {
+    let created_at = description.creation_time
+        .and_then(|t| DateTime::from_timestamp(t.seconds, t.nanos.max(0) as u32));
+    SnapshotInfo {
+        location: format!("/collections/{}/snapshots/{}", collection, description.name),
+        name: description.name,
+        created_at,
+        size_bytes: description.size.max(0) as u64,
+        checksum: description.checksum,
+    }
+}
+
+/// Name the likely cause when Qdrant refuses snapshots outright rather than failing one
+fn snapshot_error(collection: &str, error: QdrantError) -> UnifiedRagError {
+    match error {
+        QdrantError::ResponseError { ref status } if SNAPSHOTS_UNAVAILABLE_CODES.contains(&(status.code() as i32)) => {
+            UnifiedRagError::Configuration(format!(
+                "Snapshots are unavailable on this Qdrant instance for '{}' (disabled, read-only or not permitted for this API key): {}",
+                collection, status.message()
+            ))
+        }
+        e => UnifiedRagError::Qdrant(e.to_string()),
+    }
+}
+
+/// Where `scroll_memories` is in the collection
+enum ScrollState {
+    Start,
+    Next(PointId),
+    Done,
+}
+
+/// Error naming both sizes when `embedding` doesn't fit a `vector_size` collection;
+/// a mismatch means the embedding model and collection are misconfigured
+fn check_dimension(collection: &str, vector_size: u64, embedding: &[f32], id: impl std::fmt::Display) -> Result<()> {
+    if embedding.len() as u64 != vector_size {
+        return Err(UnifiedRagError::Configuration(format!(
+            "Embedding for memory {} has {} dimensions but collection '{}' stores {}; check EMBEDDING_MODEL and QDRANT_COLLECTION",
+            id, embedding.len(), collection, vector_size
+        )));
+    }
+    Ok(())
+}
+
+/// A memory's `metadata` payload object, with the `category_path` ancestors used
+/// for prefix category filters
+fn metadata_payload(metadata: &MemoryMetadata) -> Result<serde_json::Value> {
+    let mut json = serde_json::to_value(metadata)?;
+    if let Some(ref category) = metadata.category {
+        json["category_path"] = serde_json::json!(category_prefixes(category));
+    }
+    Ok(json)
+}
+
+/// Qdrant conditions for the category/tags/instance filters shared by search and scans.
+/// Every tag must match unless `tag_min_match` asks for at least that many; any
+/// listed instance matches. Prefix category matches use the `category_path`
+/// ancestors written at index time.
+fn metadata_conditions(
+    category: Option<&str>,
+    category_mode: CategoryMatchMode,
+    tags: Option<&[String]>,
+    tag_min_match: Option<usize>,
+    instances: Option<&[String]>,
+) -> Vec<Condition> {
+    let mut conditions = vec![];
+    
+    if let Some(category) = category {
+        let field = match category_mode {
+            CategoryMatchMode::Exact => "metadata.category",
+            CategoryMatchMode::Prefix => "metadata.category_path",
+        };
+        conditions.push(Condition::matches(field, category_mode.normalize(category).to_string()));
+    }
+    
+    if let Some(tags) = tags {
+        let tag_conditions = tags
+            .iter()
+            .map(|tag| Condition::matches("metadata.tags", tag.clone()));
+        match tag_min_match {
+            Some(min_count) => conditions.push(Filter::min_should(min_count as u64, tag_conditions).into()),
+            None => conditions.extend(tag_conditions),
+        }
+    }
+    
+    if let Some(instances) = instances {
+        conditions.push(Condition::matches("instance_id", instances.to_vec()));
+    }
+    
+    conditions
+}
+
+/// `metadata_conditions` for a `MemoryFilter`
+fn filter_conditions(filter: &MemoryFilter) -> Vec<Condition> {
+    metadata_conditions(
+        filter.category_filter.as_deref(),
+        filter.category_match_mode,
+        filter.tags_filter.as_deref(),
+        filter.tag_min_match,
+        filter.instance_filter.as_deref(),
+    )
+}
+
+/// Exact-match conditions on `metadata.extra.<key>`. Values other than strings,
+/// integers and booleans can't be matched exactly and are rejected before this.
+fn extra_conditions(filters: &BTreeMap<String, serde_json::Value>) -> Vec<Condition> {
+    filters
+        .iter()
+        .filter_map(|(key, value)| {
+            let field = format!("metadata.extra.{}", key);
+            match value {
+                serde_json::Value::String(s) => Some(Condition::matches(field, s.clone())),
+                serde_json::Value::Bool(b) => Some(Condition::matches(field, *b)),
+                serde_json::Value::Number(n) => n.as_i64().map(|i| Condition::matches(field, i)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SearchLayer for QdrantSearch {
+    async fn index(&self, memory: &Memory) -> Result<()> {
+        let point = self.point(memory).await?;
         self.client
             .upsert_points(UpsertPointsBuilder::new(&self.collection_name, vec![point]))
             .await
@@ -164,6 +1190,21 @@ impl SearchLayer for QdrantSearch {
         Ok(())
     }
     
+    async fn index_many(&self, memories: &[Memory]) -> Result<()> {
+        for chunk in memories.chunks(self.upsert_batch_size) {
+            let mut points = Vec::with_capacity(chunk.len());
+            for memory in chunk {
+                points.push(self.point(memory).await?);
+            }
+            self.client
+                .upsert_points(UpsertPointsBuilder::new(&self.collection_name, points))
+                .await
+                .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        }
+        
+        Ok(())
+    }
+    
     async fn delete(&self, id: &str) -> Result<()> {
         self.client
             .delete_points(
@@ -176,7 +1217,43 @@ impl SearchLayer for QdrantSearch {
         Ok(())
     }
     
+    async fn delete_by_filter(&self, filter: &MemoryFilter) -> Result<u64> {
+        let conditions = filter_conditions(filter);
+        if conditions.is_empty() {
+            return Err(UnifiedRagError::SearchError("refusing to delete with an empty filter".to_string()));
+        }
+        self.delete_matching(conditions).await
+    }
+    
+    async fn update_payload(&self, id: &str, payload: serde_json::Value) -> Result<()> {
+        let payload: Payload = serde_json::from_value(payload)?;
+        self.client
+            .set_payload(
+                SetPayloadPointsBuilder::new(&self.collection_name, payload)
+                    .points_selector(PointsIdsList { ids: vec![PointId::from(id)] })
+                    .wait(true)
+            )
+            .await
+            .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+        
+        Ok(())
+    }
+    
     async fn update_embedding(&self, id: &str, embedding: Vec<f32>) -> Result<()> {
+        self.check_dimension(&embedding, id)?;
+        if self.named_vectors {
+            // Replace only the content vector, leaving the summary intact
+            let vectors = HashMap::from([(VectorName::Content.as_str().to_string(), embedding)]);
+            self.client
+                .update_vectors(UpdatePointVectorsBuilder::new(
+                    &self.collection_name,
+                    vec![PointVectors { id: Some(PointId::from(id)), vectors: Some(vectors.into()) }],
+                ))
+                .await
+                .map_err(|e| UnifiedRagError::Qdrant(e.to_string()))?;
+            return Ok(());
+        }
+        
         // Qdrant requires re-indexing the entire point to update embedding
         // First, get the existing point
         let existing_points = self.client
@@ -204,4 +1281,23 @@ impl SearchLayer for QdrantSearch {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn check_dimension_accepts_matching_vectors() {
+        assert!(check_dimension("unified_rag", 3, &[0.1, 0.2, 0.3], "m1").is_ok());
+    }
+    
+    #[test]
+    fn check_dimension_names_both_sizes_on_mismatch() {
+        let error = check_dimension("unified_rag", 768, &vec![0.0; 1536], "m1").unwrap_err();
+        assert_eq!(error.code(), "CONFIGURATION_ERROR");
+        let message = error.to_string();
+        assert!(message.contains("1536 dimensions"), "{}", message);
+        assert!(message.contains("'unified_rag' stores 768"), "{}", message);
+    }
+}
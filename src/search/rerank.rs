@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::RerankConfig;
+use crate::error::{Result, UnifiedRagError};
+use crate::models::Memory;
+
+/// Client for a Cohere/Jina-style `/rerank` HTTP endpoint scoring (query, document) pairs
+pub struct Reranker {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    query: &'a str,
+    documents: Vec<&'a str>,
+    top_n: usize,
+}
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankHit>,
+}
+
+#[derive(Deserialize)]
+struct RerankHit {
+    index: usize,
+    relevance_score: f32,
+}
+
+impl Reranker {
+    /// Build a reranker, or `None` when no `RERANK_URL` is configured
+    pub fn from_config(config: &RerankConfig) -> Option<Self> {
+        let url = config.url.clone()?;
+        Some(Self {
+            client: reqwest::Client::new(),
+            url,
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+        })
+    }
+    
+    /// Rescore `candidates` against `query` and return the best `top_k`, with the
+    /// cross-encoder score in `relevance_score`
+    pub async fn rerank(&self, query: &str, candidates: Vec<Memory>, top_k: usize) -> Result<Vec<Memory>> {
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+        
+        let body = RerankRequest {
+            model: self.model.as_deref(),
+            query,
+            documents: candidates.iter().map(|m| m.content.as_str()).collect(),
+            top_n: top_k.min(candidates.len()),
+        };
+        
+        let mut http = self.client.post(&self.url).json(&body);
+        if let Some(ref key) = self.api_key {
+            http = http.bearer_auth(key);
+        }
+        
+        let response: RerankResponse = http
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| UnifiedRagError::SearchError(format!("Rerank request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| UnifiedRagError::SearchError(format!("Invalid rerank response: {}", e)))?;
+        
+        let mut slots: Vec<Option<Memory>> = candidates.into_iter().map(Some).collect();
+        let mut hits = response.results;
+        hits.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        
+        let reranked = hits
+            .into_iter()
+            .filter_map(|hit| {
+                let mut memory = slots.get_mut(hit.index)?.take()?;
+                memory.relevance_score = hit.relevance_score;
+                Some(memory)
+            })
+            .take(top_k)
+            .collect();
+        Ok(reranked)
+    }
+}
@@ -1,66 +1,108 @@
 use anyhow::Result;
 use rmcp::{
     handler::server::{router::tool::ToolRouter, tool::Parameters},
-    model::{CallToolResult, Content, ErrorData},
+    model::{CallToolResult, Content, ErrorCode, ErrorData},
     ServerHandler,
 };
 use rmcp_macros::{tool, tool_router, tool_handler};
+use std::borrow::Cow;
 use std::sync::Arc;
 use std::future::Future;
+use std::path::PathBuf;
+use std::time::Instant;
+use std::collections::{BTreeMap, HashSet};
+use futures::{FutureExt, TryStreamExt};
+use chrono::Utc;
+use uuid::Uuid;
 use deadpool_redis::{Config as RedisConfig, Runtime};
 use qdrant_client::Qdrant;
-use crate::tools::{RagSearchParams, RagStoreParams};
-use crate::cache::{CacheLayer, redis_cache::RedisCache};
-use crate::search::{SearchLayer, qdrant_search::QdrantSearch, embeddings::EmbeddingGenerator};
-use crate::models::SearchRequest;
+use crate::tools::{
+    self,
+    RagAggregateParams, RagClearParams, RagCountTokensParams, RagDeleteByFilterParams, RagExportChainParams, RagExportCsvParams, RagGetManyParams, RagImportVaultParams, RagMergeParams, RagLinkParams, RagMigrateModelParams, RagMoveParams, RagReembedParams, RagRelationsParams, RagSearchByEmbeddingParams, RagSearchParams, RagTagCooccurrenceParams, RagTagsParams,
+    RagStoreAndRelateParams, RagStoreParams, RagSuggestTagsParams, RagUnlinkParams,
+};
+use crate::cache::{CacheLayer, CachedSearch, InstanceStatsResult, InstancesResult, redis_cache::RedisCache};
+use crate::search::{
+    SearchLayer, fusion, qdrant_search::{self, QdrantSearch}, embeddings::{self, EmbeddingGenerator}, rerank::Reranker,
+};
+use crate::ingest::obsidian;
+use crate::export;
+use crate::models::{
+    AggregateRequest, CategoryMatchMode, CheckStatus, ClearResult, CountTokensResult, DeleteByFilterResult, ExportChainResult, ExportCsvResult, GetManyResult, EmbeddingEstimate, ImportFailure, ImportVaultResult, LinkResult, Memory,
+    MemoryFilter, MemoryMetadata,
+    MergeResult, MigrateModelResult, MoveResult, ReembedResult, Relation, RelationsResult, SearchMode, SearchRequest, SearchTimings, Snippet, StoreTimings, SearchResult, StoreAndRelateResult, StoreRequest, StoreResult, SuggestTagsResult, TagSuggestion, TagsResult, UnlinkResult,
+    ValidateResult, ValidationCheck, VectorName,
+};
+use crate::error::{self, UnifiedRagError, INTERNAL_ERROR, INVALID_INPUT};
+use crate::config::{Config, KeyScheme, StorageMode};
+use crate::ratelimit::RateLimiter;
+use crate::text;
+use crate::tokenizer::{Encoding, Tokenizers};
+
+/// JSON-RPC error code returned when a tool's rate limit is exceeded
+const RATE_LIMITED_ERROR_CODE: i32 = -32029;
+
+/// Queued memories loaded per Redis round trip when retrying deferred embeddings
+const EMBEDDING_QUEUE_BATCH: usize = 50;
+
+/// Failure messages `rag_migrate_model` returns; the rest are only counted
+const MIGRATION_ERRORS_REPORTED: usize = 20;
+
+/// Most ids one `rag_get_many` call may request
+const GET_MANY_MAX_IDS: usize = 100;
+
+/// Points whose payloads `rag_validate` tries to deserialize
+const VALIDATE_SAMPLE_POINTS: u32 = 16;
+
+/// Most similar memories one `rag_suggest_tags` call may draw tags from
+const SUGGEST_TAGS_MAX_CANDIDATES: usize = 100;
+
+/// What `migrate_page` did with one scroll page
+#[derive(Default)]
+struct MigratedPage {
+    migrated: usize,
+    already_migrated: usize,
+    errors: Vec<String>,
+}
 
 #[derive(Clone)]
 pub struct UnifiedRagService {
     tool_router: ToolRouter<Self>,
-    redis_pool: Arc<deadpool_redis::Pool>,
+    config: Arc<Config>,
     qdrant_client: Arc<Qdrant>,
     cache: Arc<RedisCache>,
     search: Arc<QdrantSearch>,
+    embeddings: Arc<EmbeddingGenerator>,
+    reranker: Option<Arc<Reranker>>,
+    rate_limiter: Arc<RateLimiter>,
+    tokenizers: Arc<Tokenizers>,
     instance_id: String,
 }
 
 impl UnifiedRagService {
     pub async fn new() -> Result<Self> {
         // Load configuration from environment
-        let redis_host = std::env::var("REDIS_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let redis_port = std::env::var("REDIS_PORT").unwrap_or_else(|_| "6379".to_string());
-        let redis_password = std::env::var("REDIS_PASSWORD").ok();
-        let instance_id = std::env::var("INSTANCE_ID").unwrap_or_else(|_| "CC".to_string());
+        let config = Arc::new(Config::from_env()?);
+        let instance_id = config.instance_id.clone();
         
         // Configure Redis pool
-        let redis_url = if let Some(password) = redis_password {
-            format!("redis://:{}@{}:{}/0", password, redis_host, redis_port)
-        } else {
-            format!("redis://{}:{}/0", redis_host, redis_port)
-        };
-        
-        let cfg = RedisConfig::from_url(redis_url);
+        let cfg = RedisConfig::from_url(config.redis_url());
         let redis_pool = Arc::new(cfg.create_pool(Some(Runtime::Tokio1))?);
         
-        // Configure Qdrant client
-        let qdrant_host = std::env::var("QDRANT_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let qdrant_port = std::env::var("QDRANT_PORT")
-            .unwrap_or_else(|_| "6334".to_string())
-            .parse::<u16>()
-            .unwrap_or(6334);
-        
         // Use the Qdrant protocol from env var, defaulting to http for local instances
-        let qdrant_protocol = std::env::var("QDRANT_PROTOCOL").unwrap_or_else(|_| "http".to_string());
-        let qdrant_url = format!("{}://{}:{}", qdrant_protocol, qdrant_host, qdrant_port);
+        let qdrant_url = config.qdrant_url();
         
         tracing::info!("Connecting to Qdrant at: {}", qdrant_url);
         
         // Create Qdrant client with custom configuration
         // Use port 6333 for HTTP API (not gRPC which uses 6334)
+        let mut qdrant_config = Qdrant::from_url(&qdrant_url)
+            .timeout(std::time::Duration::from_secs(config.qdrant.timeout_secs))
+            .connect_timeout(std::time::Duration::from_secs(config.qdrant.connect_timeout_secs));
+        qdrant_config.set_pool_size(config.qdrant.pool_size);
+        
         let qdrant_client = Arc::new(
-            match Qdrant::from_url(&qdrant_url)
-                .timeout(std::time::Duration::from_secs(30))
-                .build() {
+            match qdrant_config.build() {
                 Ok(client) => client,
                 Err(e) => {
                     tracing::error!("Failed to create Qdrant client: {}", e);
@@ -78,147 +120,3092 @@ impl UnifiedRagService {
             }
         );
         
+        // Containers often start before Qdrant is listening; wait instead of failing
+        qdrant_search::wait_until_ready(&qdrant_client, config.qdrant.startup_retries).await?;
+        
         // Initialize cache and search layers
-        let cache = Arc::new(RedisCache::new(redis_pool.clone(), &instance_id));
+        let cache = Arc::new(
+            RedisCache::new(redis_pool, &instance_id, &config.redis)
+                .with_embedding_model(&config.openai.model)
+                .with_max_results(config.max_results),
+        );
         
         // Try to create embedding generator
-        let embedding_generator = match EmbeddingGenerator::new() {
-            Ok(eg) => Arc::new(eg),
+        let openai_timeout = std::time::Duration::from_secs(config.openai.timeout_secs);
+        tracing::info!("OpenAI request timeout: {:?}", openai_timeout);
+        let embedding_generator = match EmbeddingGenerator::new(openai_timeout) {
+            Ok(mut eg) => {
+                if config.openai.hash_embeddings {
+                    tracing::warn!(
+                        "HASH_EMBEDDINGS is on: embeddings are {}-dimensional word hashes, not OpenAI's; use only for tests",
+                        config.openai.dimension
+                    );
+                    eg = eg.with_hash_embeddings(config.openai.dimension as usize);
+                }
+                if !eg.is_available() {
+                    tracing::warn!(
+                        "OPENAI_API_KEY not set; starting without embeddings. Cache operations work, semantic search and Qdrant indexing fail"
+                    );
+                }
+                Arc::new(
+                    eg.with_model(&config.openai.model)
+                        .with_batching(config.openai.batch_size, config.openai.batch_concurrency),
+                )
+            }
             Err(e) => {
                 tracing::error!("Failed to create embedding generator: {}. Some features may be unavailable.", e);
                 return Err(e.into());
             }
         };
         
-        let collection_name = std::env::var("QDRANT_COLLECTION")
-            .unwrap_or_else(|_| "unified_rag".to_string());
+        let collection_name = config.qdrant.collection_name.clone();
+        let vector_size = detect_dimension(&cache, &embedding_generator, config.openai.dimension).await;
+        
+        if config.qdrant.auto_create {
+            tracing::info!("QDRANT_AUTO_CREATE is on: collection {} is created if it is missing", collection_name);
+        } else {
+            tracing::info!("QDRANT_AUTO_CREATE is off: startup fails if collection {} is missing", collection_name);
+        }
         
         // Try to initialize Qdrant search
-        let search = match QdrantSearch::new(qdrant_client.clone(), collection_name, embedding_generator).await {
-            Ok(s) => Arc::new(s),
+        let search = match QdrantSearch::with_alias(
+            qdrant_client.clone(),
+            collection_name,
+            embedding_generator.clone(),
+            vector_size,
+            &config.qdrant.hnsw,
+            config.qdrant.auto_create,
+        ).await {
+            Ok(s) => Arc::new(
+                s.with_minimal_payload(!config.storage_mode.full_qdrant_payload())
+                    .with_upsert_batch_size(config.qdrant.upsert_batch_size)
+                    .with_payload_fields(config.qdrant.payload_fields.clone()),
+            ),
             Err(e) => {
                 tracing::error!("Failed to initialize Qdrant search layer: {}", e);
                 return Err(e.into());
             }
         };
         
+        // text_match filters on payload content, which redis_primary leaves empty
+        if config.qdrant.text_index && config.storage_mode.full_qdrant_payload() {
+            match search.ensure_text_index().await {
+                Ok(true) => tracing::info!("Created full-text index on Qdrant payload field content"),
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to create the full-text index on content; text_match will be slow: {}", e),
+            }
+        }
+        
+        let reranker = Reranker::from_config(&config.rerank).map(Arc::new);
+        let rate_limiter = Arc::new(RateLimiter::new(
+            config.rate_limit.per_tool.clone(),
+            config.rate_limit.default,
+        ));
+        if let Some(ref url) = config.rerank.url {
+            tracing::info!("Reranking available via {}", url);
+        }
+        
         tracing::info!(
             "UnifiedRAG service initialized - Instance: {}, Redis: {}:{}, Qdrant: {}",
-            instance_id, redis_host, redis_port, qdrant_url
+            instance_id, config.redis.host, config.redis.port, qdrant_url
         );
+        let tokenizers = Arc::new(Tokenizers::new(config.openai.tiktoken_dir.as_ref().map(PathBuf::from)));
         
         Ok(Self {
             tool_router: Self::tool_router(),
-            redis_pool,
+            config,
             qdrant_client,
             cache,
             search,
+            embeddings: embedding_generator,
+            reranker,
+            rate_limiter,
+            tokenizers,
             instance_id,
         })
     }
-}
-
-/// Implementation of MCP tools using rmcp macros
-#[tool_router]
-impl UnifiedRagService {
-    /// Search for memories using hybrid L1/L2 retrieval
-    #[tool(description = "Search for memories using hybrid L1/L2 retrieval with Redis caching and Qdrant semantic search")]
-    pub async fn rag_search(
+    
+    /// Address the health endpoints should listen on, if enabled
+    pub fn health_addr(&self) -> Option<&str> {
+        self.config.health_addr.as_deref()
+    }
+    
+    /// Check each backend the service depends on, returning `(name, error)` per backend
+    pub async fn readiness(&self) -> Vec<(&'static str, Option<String>)> {
+        let (redis, qdrant, openai) = tokio::join!(
+            self.cache.ping(),
+            self.qdrant_client.health_check(),
+            self.embeddings.check_connection(),
+        );
+        let mut checks = vec![
+            ("redis", redis.err().map(|e| e.to_string())),
+            ("qdrant", qdrant.err().map(|e| e.to_string())),
+        ];
+        // Running without an API key is a deliberate cache-only setup, not an outage
+        if self.embeddings.is_available() {
+            checks.push(("openai", openai.err().map(|e| e.to_string())));
+        }
+        checks
+    }
+    
+    /// Pre-fill the embedding cache for the configured `WARM_QUERIES`.
+    ///
+    /// Queries already cached cost nothing; failures are logged and skipped.
+    pub async fn warm_cache(&self) {
+        let queries = &self.config.warmup.queries;
+        if !self.config.warmup.enabled || queries.is_empty() || !self.embeddings.is_available() {
+            return;
+        }
+        
+        let start = std::time::Instant::now();
+        let mut warmed = 0usize;
+        for query in queries {
+            match self.embed(&text::normalize(query)).await {
+                Ok(_) => warmed += 1,
+                Err(e) => tracing::warn!("Failed to warm embedding for {:?}: {}", query, e),
+            }
+        }
+        tracing::info!(
+            "Warmed embedding cache for {}/{} queries in {:?}",
+            warmed, queries.len(), start.elapsed()
+        );
+    }
+    
+    /// Reject the call when `tool` has used up its configured requests/sec
+    fn check_rate_limit(&self, tool: &str) -> std::result::Result<(), ErrorData> {
+        self.rate_limiter.check(tool).map_err(|rate| {
+            tracing::warn!("Rate limit exceeded for {} ({} req/s)", tool, rate);
+            ErrorData::new(
+                ErrorCode(RATE_LIMITED_ERROR_CODE),
+                format!("Rate limit exceeded for {}: at most {} requests per second", tool, rate),
+                Some(serde_json::json!({ "code": error::RATE_LIMITED, "tool": tool, "requests_per_second": rate })),
+            )
+        })
+    }
+    
+    /// Validate `rag_store` arguments and store them, replaying the recorded result
+    /// for a known idempotency key
+    async fn store_params(&self, params: RagStoreParams) -> std::result::Result<StoreResult, ErrorData> {
+        let started = Instant::now();
+        if params.content.trim().is_empty() {
+            return Err(invalid_input("content must not be empty"));
+        }
+        if let Some(importance) = params.importance {
+            if !(1..=10).contains(&importance) {
+                return Err(invalid_input("importance must be between 1 and 10"));
+            }
+        }
+        let parent_id = params.parent_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| invalid_input(format!("Invalid parent_id: {}", e)))?;
+        let model = embedding_model_param(params.model.as_deref())?;
+        let summary = params.summary.filter(|s| !s.trim().is_empty());
+        validate_summary_model(
+            model.as_deref(),
+            self.embeddings.model(),
+            summary.is_some(),
+            self.search.has_named_vectors(),
+        )?;
+        let max_tokens = self.config.openai.max_content_tokens;
+        let over_limit = if params.embedding.is_none() && !params.truncate_long_content {
+            let tokens = self.count_tokens(model.as_deref(), &params.content).await;
+            if tokens > max_tokens && !self.config.openai.chunk_long_content {
+                return Err(invalid_input(
+                    format!(
+                        "content is {} tokens, over MAX_CONTENT_TOKENS ({}); split it, set truncate_long_content or enable CHUNK_LONG_CONTENT",
+                        tokens, max_tokens
+                    ),
+                ));
+            }
+            tokens > max_tokens
+        } else {
+            false
+        };
+        if let Some(ref embedding) = params.embedding {
+            let expected = self.search.vector_size();
+            if embedding.len() as u64 != expected {
+                return Err(invalid_input(
+                    format!("embedding has {} dimensions, collection expects {}", embedding.len(), expected),
+                ));
+            }
+            if embedding.iter().any(|v| !v.is_finite()) {
+                return Err(invalid_input("embedding contains non-finite values"));
+            }
+        }
+        
+        for key in params.extra.keys() {
+            validate_extra_key(key)?;
+        }
+        let (tags, tags_truncated) = self.limit_tags(normalize_tags(&params.tags)).map_err(invalid_input)?;
+        
+        let request = StoreRequest {
+            content: params.content,
+            category: params.category,
+            tags,
+            importance: params.importance,
+            chain_id: params.chain_id,
+            parent_id,
+            framework: params.framework,
+            embedding: params.embedding,
+            summary,
+            dedup: params.dedup,
+            truncate: params.truncate_long_content,
+            auto_link: params.auto_link,
+            strip_markdown: params.strip_markdown.unwrap_or(self.config.openai.strip_markdown),
+            strip_html: params.strip_html.unwrap_or(self.config.openai.strip_html),
+            extra: params.extra,
+            model,
+        };
+        let idempotency_key = params.idempotency_key.filter(|k| !k.trim().is_empty());
+        let want_timings = params.timings;
+        
+        if let Some(ref key) = idempotency_key {
+            match self.cache.idempotent_result(key).await {
+                Ok(Some(result)) => {
+                    tracing::debug!("Idempotency key {} already stored {}", key, result.memory_id);
+                    return Ok(result);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Idempotency lookup failed for {}: {}", key, e),
+            }
+        }
+        
+        let result = if over_limit {
+            self.store_chunked(request, "rag_store").await
+        } else {
+            self.store_memory(request, "rag_store").await
+        };
+        let mut result = result.map_err(|e| {
+            tracing::error!("Store failed: {}", e);
+            internal_error(format!("Store failed: {}", e), e.code())
+        })?;
+        result.tags_truncated = tags_truncated;
+        
+        // A replay does no work, so it shouldn't report this call's timings
+        let mut timings = result.timings.take().unwrap_or_default();
+        if let Some(ref key) = idempotency_key {
+            if let Err(e) = self.cache.set_idempotent_result(key, &result).await {
+                tracing::warn!("Failed to record idempotency key {}: {}", key, e);
+            }
+        }
+        if want_timings {
+            timings.total_ms = elapsed_ms(started);
+            result.timings = Some(timings);
+        }
+        
+        Ok(result)
+    }
+    
+    /// Memories closest to a stored one by its content vector as read back from Qdrant,
+    /// leaving out the memory itself and any chunks it was stored as
+    async fn related_memories(
         &self,
-        params: Parameters<RagSearchParams>,
-    ) -> std::result::Result<CallToolResult, ErrorData> {
+        stored: &StoreResult,
+        limit: usize,
+        threshold: f32,
+    ) -> crate::error::Result<Vec<Memory>> {
+        let id = stored.memory_id.to_string();
+        let embedding = self.search
+            .get_memory(&id)
+            .await?
+            .and_then(|memory| memory.embedding)
+            .ok_or_else(|| UnifiedRagError::SearchError(format!("memory {} has no vector in Qdrant", id)))?;
+        
         let request = SearchRequest {
-            query: params.0.query,
-            limit: Some(params.0.limit),
-            threshold: Some(params.0.threshold),
-            category_filter: params.0.category_filter,
-            tags_filter: params.0.tags_filter,
-            instance_filter: params.0.instance_filter,
-            hybrid_mode: params.0.hybrid_mode,
-        };
-        
-        // Try cache first if hybrid mode
-        let result = if request.hybrid_mode {
-            match self.cache.search_cached(&request).await {
-                Ok(cache_results) if !cache_results.is_empty() => {
-                    // Return cache results
-                    serde_json::json!({
-                        "memories": cache_results,
-                        "source": "cache",
-                        "count": cache_results.len()
-                    })
-                }
-                Ok(_) | Err(_) => {
-                    // Fall back to Qdrant (empty cache results or cache error)
-                    match self.search.search(&request).await {
-                        Ok(search_result) => {
-                            serde_json::to_value(search_result)
-                                .map_err(|e| ErrorData::internal_error(format!("Failed to serialize search results: {}", e), None))?
-                        }
-                        Err(e) => {
-                            tracing::error!("Search failed: {}", e);
-                            return Err(ErrorData::internal_error(
-                                format!("Search failed: {}. Please check that Qdrant is running and accessible.", e),
-                                None
-                            ));
-                        }
-                    }
+            query: String::new(),
+            limit: Some(limit),
+            threshold: Some(threshold),
+            category_filter: None,
+            category_match_mode: CategoryMatchMode::Exact,
+            tags_filter: None,
+            tag_min_match: None,
+            instance_filter: None,
+            chain_filter: None,
+            extra_filters: None,
+            mode: SearchMode::SemanticOnly,
+            vector: VectorName::Content,
+            bypass_cache: false,
+            cache_ttl: None,
+            hnsw_ef: None,
+            all_instances: false,
+            exclude_ids: std::iter::once(stored.memory_id).chain(stored.chunk_ids.iter().copied()).collect(),
+            text_match: None,
+            model: None,
+        };
+        
+        let result = self.search.search_with_embedding(&request, embedding).await?;
+        self.hydrate(result.memories).await
+    }
+    
+    /// This instance's chain members in order, each with its memory when it can be
+    /// loaded: from Redis, then from Qdrant unless Redis holds the only full copy.
+    /// Repeated links keep their first position.
+    async fn load_chain(&self, chain_id: &str) -> crate::error::Result<Vec<(String, Option<Memory>)>> {
+        let mut ids: Vec<String> = Vec::new();
+        for id in self.cache.chain_members(chain_id).await? {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        
+        let keys: Vec<(String, String)> = ids.iter().map(|id| (self.instance_id.clone(), id.clone())).collect();
+        let mut entries: Vec<(String, Option<Memory>)> = ids.into_iter().zip(self.cache.get_many(&keys).await?).collect();
+        
+        let missing: Vec<String> = entries.iter().filter(|(_, m)| m.is_none()).map(|(id, _)| id.clone()).collect();
+        if !missing.is_empty() && self.config.storage_mode != StorageMode::RedisPrimary {
+            for memory in self.search.get_memories(&missing).await? {
+                let id = memory.id.to_string();
+                if let Some(entry) = entries.iter_mut().find(|(entry_id, _)| *entry_id == id) {
+                    entry.1 = Some(memory);
                 }
             }
+        }
+        
+        Ok(entries)
+    }
+    
+    /// Apply `MAX_TAGS` to normalized tags: keep the first `MAX_TAGS` (reporting true)
+    /// with `TRUNCATE_EXCESS_TAGS`, otherwise reject the write with the reason
+    fn limit_tags(&self, mut tags: Vec<String>) -> std::result::Result<(Vec<String>, bool), String> {
+        match self.config.max_tags {
+            Some(max) if tags.len() > max => {
+                if !self.config.truncate_excess_tags {
+                    return Err(format!("{} tags exceed MAX_TAGS ({})", tags.len(), max));
+                }
+                tags.truncate(max);
+                Ok((tags, true))
+            }
+            _ => Ok((tags, false)),
+        }
+    }
+    
+    /// Add or remove tags on a memory without touching its content or vectors: Redis
+    /// gets the new value and tag index sets, Qdrant a metadata payload update
+    async fn edit_tags(&self, params: RagTagsParams, add: bool) -> std::result::Result<TagsResult, ErrorData> {
+        let id = Uuid::parse_str(&params.id)
+            .map_err(|e| invalid_input(format!("Invalid id: {}", e)))?
+            .to_string();
+        let tags = normalize_tags(&params.tags);
+        if tags.is_empty() {
+            return Err(invalid_input("tags must name at least one tag"));
+        }
+        
+        let (mut memory, in_cache) = self.find_memory(&id).await?;
+        let mut tags_truncated = false;
+        let (added, removed): (Vec<String>, Vec<String>) = if add {
+            let added: Vec<String> = tags.into_iter().filter(|t| !memory.metadata.tags.contains(t)).collect();
+            let combined = memory.metadata.tags.iter().chain(&added).cloned().collect();
+            let (kept, truncated) = self.limit_tags(combined).map_err(invalid_input)?;
+            tags_truncated = truncated;
+            (added.into_iter().filter(|t| kept.contains(t)).collect(), Vec::new())
         } else {
-            // Direct Qdrant search
-            match self.search.search(&request).await {
-                Ok(search_result) => {
-                    serde_json::to_value(search_result)
-                        .map_err(|e| ErrorData::internal_error(format!("Failed to serialize search results: {}", e), None))?
+            let removed = tags.into_iter().filter(|t| memory.metadata.tags.contains(t)).collect();
+            (Vec::new(), removed)
+        };
+        
+        let mut result = TagsResult {
+            memory_id: memory.id,
+            tags: memory.metadata.tags.clone(),
+            changed: !added.is_empty() || !removed.is_empty(),
+            cached: false,
+            indexed: false,
+            tags_truncated,
+        };
+        if !result.changed {
+            return Ok(result);
+        }
+        
+        memory.metadata.tags.retain(|t| !removed.contains(t));
+        memory.metadata.tags.extend(added.iter().cloned());
+        memory.updated_at = Utc::now();
+        
+        result.cached = in_cache && match self.cache.update_tags(&id, &memory, &added, &removed).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to update tags of memory {} in Redis: {}", id, e);
+                false
+            }
+        };
+        result.indexed = match self.search.update_metadata(&id, &memory.metadata, memory.updated_at).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to update tags of memory {} in Qdrant: {}", id, e);
+                false
+            }
+        };
+        if !result.cached && !result.indexed {
+            return Err(internal_error(
+                format!("Failed to update tags of memory {} in both Redis and Qdrant", id),
+                INTERNAL_ERROR,
+            ));
+        }
+        
+        result.tags = memory.metadata.tags;
+        Ok(result)
+    }
+    
+    /// Reject searches of the summary vector on legacy single-vector collections
+    fn check_vector_available(&self, vector: VectorName) -> std::result::Result<(), ErrorData> {
+        if vector != VectorName::Content && !self.search.has_named_vectors() {
+            return Err(invalid_input(
+                format!("the collection has a single unnamed vector; '{}' search is unavailable", vector.as_str()),
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Embed text, going through the Redis embedding cache first
+    async fn embed(&self, text: &str) -> crate::error::Result<Vec<f32>> {
+        match self.cache.get_embedding(text).await {
+            Ok(Some(embedding)) => return Ok(embedding),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Embedding cache lookup failed: {}", e),
+        }
+        
+        let embedding = self.embeddings.generate_embedding(text).await?;
+        if let Err(e) = self.cache.set_embedding(text, &embedding).await {
+            tracing::warn!("Failed to cache embedding: {}", e);
+        }
+        Ok(embedding)
+    }
+    
+    /// Embed text with `model` when given, otherwise as `embed` does. The model's
+    /// vectors must fit the collection: a dimension already probed for it is checked
+    /// before any call, and every fresh vector after it.
+    async fn embed_with(&self, model: Option<&str>, text: &str) -> crate::error::Result<Vec<f32>> {
+        let Some(model) = model.filter(|model| *model != self.embeddings.model()) else {
+            return self.embed(text).await;
+        };
+        let expected = self.search.vector_size();
+        let mismatch = |dimension: u64| UnifiedRagError::Configuration(format!(
+            "model {} produces {}-dimensional vectors but the collection expects {}",
+            model, dimension, expected
+        ));
+        if let Ok(Some(dimension)) = self.cache.embedding_dimension(model).await {
+            if dimension != expected {
+                return Err(mismatch(dimension));
+            }
+        }
+        
+        // Cached per model, like the default model's embeddings
+        let cache = RedisCache::clone(&self.cache).with_embedding_model(model);
+        match cache.get_embedding(text).await {
+            Ok(Some(embedding)) => return Ok(embedding),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Embedding cache lookup failed: {}", e),
+        }
+        
+        let embedding = self.embeddings.generate_embedding_with(model, text).await?;
+        let dimension = embedding.len() as u64;
+        if let Err(e) = self.cache.set_embedding_dimension(model, dimension).await {
+            tracing::warn!("Failed to cache embedding dimension: {}", e);
+        }
+        if dimension != expected {
+            return Err(mismatch(dimension));
+        }
+        if let Err(e) = cache.set_embedding(text, &embedding).await {
+            tracing::warn!("Failed to cache embedding: {}", e);
+        }
+        Ok(embedding)
+    }
+    
+    /// Embed and write a memory to both Redis and Qdrant.
+    ///
+    /// A failure in one backend is reported through the `cached`/`indexed` flags;
+    /// only a failure in both is an error.
+    async fn store_memory(&self, request: StoreRequest, source: &str) -> crate::error::Result<StoreResult> {
+        let started = Instant::now();
+        let mut timings = StoreTimings::default();
+        let mut embedding_truncated = false;
+        let mut embedding_skipped = false;
+        let (embedding, embedding_generated) = match request.embedding {
+            Some(embedding) => (Some(embedding), false),
+            None if self.below_min_embed_chars(&request) => {
+                embedding_skipped = true;
+                (None, false)
+            }
+            None => {
+                let prepared = text::preprocess(&request.content, request.strip_markdown, request.strip_html);
+                let input = if request.truncate {
+                    let input = text::truncate_to_tokens(&prepared, self.config.openai.max_content_tokens);
+                    embedding_truncated = input.len() < prepared.len();
+                    input
+                } else {
+                    &prepared
+                };
+                let start = Instant::now();
+                let embedded = self.embed_with(request.model.as_deref(), input).await;
+                timings.embedding_ms = elapsed_ms(start);
+                match embedded {
+                    Ok(embedding) => (Some(embedding), true),
+                    // A `model` whose dimension doesn't fit won't succeed on retry either
+                    Err(e @ UnifiedRagError::Configuration(_)) => return Err(e),
+                    Err(e) if self.defers_embeddings() => {
+                        tracing::warn!("Embedding failed ({}); storing to Redis and deferring the embedding", e);
+                        (None, false)
+                    }
+                    Err(e) => return Err(e),
                 }
-                Err(e) => {
-                    tracing::error!("Search failed: {}", e);
-                    return Err(ErrorData::internal_error(
-                        format!("Search failed: {}. Please check that Qdrant is running and accessible.", e),
-                        None
-                    ));
+            }
+        };
+        let embedded = embedding.is_some();
+        let deferred = !embedded && !embedding_skipped;
+        
+        if let (true, Some(embedding)) = (request.dedup, embedding.as_deref()) {
+            let start = Instant::now();
+            let nearest = self.nearest_memory(&request.content, embedding, self.config.dedup_threshold).await?;
+            timings.qdrant_ms += elapsed_ms(start);
+            if let Some(existing) = nearest {
+                timings.total_ms = elapsed_ms(started);
+                return Ok(StoreResult {
+                    memory_id: existing,
+                    cached: false,
+                    indexed: false,
+                    embedding_generated,
+                    duplicate: true,
+                    chunk_ids: Vec::new(),
+                    embedding_truncated,
+                    embedding_deferred: false,
+                    embedding_skipped: false,
+                    linked_id: None,
+                    tags_truncated: false,
+                    content_hash: None,
+                    timings: Some(timings),
+                });
+            }
+        }
+        
+        // Looked up before indexing, so the new memory can't match itself
+        let mut parent_id = request.parent_id;
+        let mut linked_id = None;
+        let may_link = parent_id.is_none() || self.config.auto_link_overwrite_parent;
+        if let (true, true, Some(embedding)) = (request.auto_link, may_link, embedding.as_deref()) {
+            let start = Instant::now();
+            let nearest = self.nearest_memory(&request.content, embedding, self.config.auto_link_threshold).await;
+            timings.qdrant_ms += elapsed_ms(start);
+            match nearest {
+                Ok(Some(nearest)) => {
+                    parent_id = Some(nearest);
+                    linked_id = Some(nearest);
                 }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Auto-link lookup failed; storing without a link: {}", e),
             }
+        }
+        
+        let now = Utc::now();
+        let content_hash = text::content_hash(&request.content);
+        let memory = Memory {
+            id: Uuid::new_v4(),
+            instance_id: self.instance_id.clone(),
+            content: request.content,
+            embedding,
+            metadata: MemoryMetadata {
+                category: request.category,
+                tags: request.tags,
+                importance: request.importance.unwrap_or(5),
+                chain_id: request.chain_id,
+                parent_id,
+                framework: request.framework,
+                source: source.to_string(),
+                content_hash: Some(content_hash.clone()),
+                extra: request.extra,
+            },
+            created_at: now,
+            updated_at: now,
+            access_count: 0,
+            relevance_score: 0.0,
+            summary: request.summary,
+            truncated: false,
+            snippet: None,
+            filter_match: None,
         };
+        let memory_id = memory.id.to_string();
         
-        let content = Content::json(result)
-            .map_err(|e| ErrorData::internal_error(format!("Failed to create JSON content: {}", e), None))?;
-        Ok(CallToolResult::success(vec![content]))
+        let storage_mode = self.config.storage_mode;
+        let start = Instant::now();
+        let cached = storage_mode.stores_in_redis() && match self.cache.set(&memory_id, &memory, None).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to cache memory {}: {}", memory_id, e);
+                false
+            }
+        };
+        timings.redis_ms = elapsed_ms(start);
+        
+        // Without a vector there is nothing to index, at least until the queue catches up
+        let start = Instant::now();
+        let indexed = embedded && match self.search.index(&memory).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to index memory {}: {}", memory_id, e);
+                false
+            }
+        };
+        timings.qdrant_ms += elapsed_ms(start);
+        
+        // The memory must at least reach the backend holding its full copy
+        let stored = match storage_mode {
+            _ if !embedded => cached,
+            StorageMode::Dual => cached || indexed,
+            StorageMode::RedisPrimary => cached,
+            StorageMode::QdrantPrimary => indexed,
+        };
+        if !stored {
+            return Err(UnifiedRagError::ToolError(format!(
+                "Failed to store memory {} ({:?} storage: cached={}, indexed={})",
+                memory_id, storage_mode, cached, indexed
+            )));
+        }
+        
+        if deferred {
+            let start = Instant::now();
+            self.cache.enqueue_embedding(&memory_id).await.map_err(|e| {
+                UnifiedRagError::ToolError(format!(
+                    "Stored memory {} in Redis without an embedding but failed to queue it for embedding: {}",
+                    memory_id, e
+                ))
+            })?;
+            timings.redis_ms += elapsed_ms(start);
+        }
+        timings.total_ms = elapsed_ms(started);
+        
+        Ok(StoreResult {
+            memory_id: memory.id,
+            cached,
+            indexed,
+            embedding_generated,
+            duplicate: false,
+            chunk_ids: Vec::new(),
+            embedding_truncated,
+            embedding_deferred: deferred,
+            embedding_skipped,
+            linked_id,
+            tags_truncated: false,
+            content_hash: Some(content_hash),
+            timings: Some(timings),
+        })
     }
     
-    /// Store a memory with automatic embedding generation
-    #[tool(description = "Store a memory with automatic embedding generation and indexing in both Redis and Qdrant")]
-    pub async fn rag_store(
-        &self,
-        _params: Parameters<RagStoreParams>,
-    ) -> std::result::Result<CallToolResult, ErrorData> {
-        // TODO: Implement store logic
-        let result = serde_json::json!({
-            "status": "not_implemented",
-            "message": "Store functionality coming soon"
+    /// Stored content as embedded outside `rag_store`, under the global
+    /// `STRIP_MARKDOWN`/`STRIP_HTML` settings
+    fn embedding_text<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        text::preprocess(content, self.config.openai.strip_markdown, self.config.openai.strip_html)
+    }
+    
+    /// Tokens `text` takes with the encoding of `model` (default: `EMBEDDING_MODEL`), or
+    /// the conservative `estimate_tokens` when the encoding can't be loaded
+    async fn count_tokens(&self, model: Option<&str>, text: &str) -> usize {
+        let encoding = Encoding::for_model(model.unwrap_or(self.embeddings.model()));
+        let bpe = match self.tokenizers.get(encoding).await {
+            Ok(bpe) => bpe,
+            Err(e) => {
+                tracing::warn!("Falling back to estimated token count: {}", e);
+                return text::estimate_tokens(text);
+            }
+        };
+        // Long texts take a while to merge; keep them off the async workers
+        let owned = text.to_string();
+        match tokio::task::spawn_blocking(move || bpe.count(&owned)).await {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                tracing::warn!("Token count failed, falling back to estimate: {}", e);
+                text::estimate_tokens(text)
+            }
+        }
+    }
+    
+    /// Count what `rag_migrate_model` would embed with `model`: the same pages, loaded
+    /// and preprocessed the same way, counted with `model`'s encoding instead of sent.
+    /// The encoding is loaded once; if that fails every text is `estimate_tokens`'d.
+    async fn estimate_migration(&self, model: &str, source_collection: &str) -> std::result::Result<EmbeddingEstimate, ErrorData> {
+        let batch_size = self.config.openai.batch_size;
+        let max_tokens = self.config.openai.max_content_tokens;
+        let mut estimate = EmbeddingEstimate::default();
+        let bpe = match self.tokenizers.get(Encoding::for_model(model)).await {
+            Ok(bpe) => Some(bpe),
+            Err(e) => {
+                tracing::warn!("Falling back to estimated token counts: {}", e);
+                None
+            }
+        };
+        estimate.exact = bpe.is_some();
+        
+        let mut pages = std::pin::pin!(self.search.scroll_memories(None, None, None, false));
+        while let Some(page) = pages.try_next().await.map_err(|e| {
+            internal_error(format!("Scrolling {} failed: {}", source_collection, e), e.code())
+        })? {
+            let listed = page.len();
+            let memories = self.hydrate(page).await.map_err(|e| {
+                internal_error(format!("Failed to load memories from Redis: {}", e), e.code())
+            })?;
+            estimate.missing_content += listed - memories.len();
+            estimate.memories += memories.len();
+            estimate.requests += memories.len().div_ceil(batch_size);
+            
+            let texts: Vec<(String, Option<String>)> = memories
+                .iter()
+                .map(|memory| (self.embedding_text(&memory.content).into_owned(), memory.summary.clone()))
+                .collect();
+            let bpe = bpe.clone();
+            // Long texts take a while to merge; keep them off the async workers
+            let counts = tokio::task::spawn_blocking(move || {
+                let count = |text: &str| match bpe {
+                    Some(ref bpe) => bpe.count(text),
+                    None => text::estimate_tokens(text),
+                };
+                texts
+                    .iter()
+                    .map(|(content, summary)| (count(content), summary.as_deref().map(count)))
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .map_err(|e| internal_error(format!("Token count failed: {}", e), INTERNAL_ERROR))?;
+            
+            for (tokens, summary_tokens) in counts {
+                if tokens > max_tokens {
+                    estimate.over_token_limit += 1;
+                }
+                estimate.estimated_tokens += tokens as u64;
+                if let Some(summary_tokens) = summary_tokens {
+                    estimate.summaries += 1;
+                    estimate.requests += 1;
+                    estimate.estimated_tokens += summary_tokens as u64;
+                }
+            }
+        }
+        
+        estimate.usd_per_million_tokens = embeddings::price_per_million_tokens(model);
+        estimate.estimated_cost_usd = estimate
+            .usd_per_million_tokens
+            .map(|price| estimate.estimated_tokens as f64 / 1_000_000.0 * price);
+        Ok(estimate)
+    }
+    
+    /// Migrate one scroll page into `target`: skip what it already holds, load the rest,
+    /// embed it with `embeddings` and index it. Failures are recorded per memory, so a
+    /// page that can't be read fails only its own memories
+    async fn migrate_page(&self, page: Vec<Memory>, embeddings: &EmbeddingGenerator, target: &QdrantSearch) -> MigratedPage {
+        let mut outcome = MigratedPage::default();
+        let ids: Vec<String> = page.iter().map(|m| m.id.to_string()).collect();
+        let done = match target.existing_ids(&ids).await {
+            Ok(done) => done,
+            Err(e) => {
+                outcome.errors = ids.iter().map(|id| format!("{}: failed to read the target: {}", id, e)).collect();
+                return outcome;
+            }
+        };
+        outcome.already_migrated = done.len();
+        
+        let pending: Vec<Memory> = page.into_iter().filter(|m| !done.contains(&m.id.to_string())).collect();
+        let pending_ids: Vec<String> = pending.iter().map(|m| m.id.to_string()).collect();
+        let mut memories = match self.hydrate(pending).await {
+            Ok(memories) => memories,
+            Err(e) => {
+                outcome.errors = pending_ids.iter().map(|id| format!("{}: failed to load from Redis: {}", id, e)).collect();
+                return outcome;
+            }
+        };
+        if memories.len() < pending_ids.len() {
+            let loaded: HashSet<String> = memories.iter().map(|m| m.id.to_string()).collect();
+            for id in pending_ids.iter().filter(|id| !loaded.contains(*id)) {
+                outcome.errors.push(format!("{}: content missing from Redis", id));
+            }
+        }
+        
+        let inputs: Vec<Cow<str>> = memories.iter().map(|m| self.embedding_text(&m.content)).collect();
+        let vectors = embeddings
+            .generate_embeddings(inputs.iter().map(|input| input.as_ref()).collect())
+            .await;
+        let mut embedded = Vec::with_capacity(memories.len());
+        for (mut memory, vector) in memories.drain(..).zip(vectors) {
+            match vector {
+                Ok(vector) => {
+                    memory.embedding = Some(vector);
+                    embedded.push(memory);
+                }
+                Err(e) => outcome.errors.push(format!("{}: {}", memory.id, e)),
+            }
+        }
+        
+        // Upserts are idempotent, so a failed page is safe to migrate again
+        match target.index_many(&embedded).await {
+            Ok(()) => outcome.migrated += embedded.len(),
+            Err(e) => outcome.errors.extend(embedded.iter().map(|m| format!("{}: {}", m.id, e))),
+        }
+        outcome
+    }
+    
+    /// `rag_validate`'s checks, in order; once the collection can't be read the
+    /// remaining checks are reported as skipped rather than run
+    async fn validation_checks(&self) -> Vec<ValidationCheck> {
+        let check = |name: &str, status, detail: String, remediation: Option<String>| ValidationCheck {
+            name: name.to_string(),
+            status,
+            detail,
+            remediation,
+        };
+        let skipped = |name: &str| ValidationCheck {
+            name: name.to_string(),
+            status: CheckStatus::Skipped,
+            detail: "The collection could not be read".to_string(),
+            remediation: None,
+        };
+        let later = ["vector_size", "payload_indexes", "sample_payloads"];
+        let collection = &self.config.qdrant.collection_name;
+        
+        let mut checks = Vec::new();
+        let info = match self.search.collection_exists().await {
+            Ok(true) => self.search.collection_info().await,
+            Ok(false) => {
+                checks.push(check(
+                    "collection_exists",
+                    CheckStatus::Fail,
+                    format!("Qdrant has no collection or alias named '{}'", collection),
+                    Some("Restart the server to create the collection, or set QDRANT_COLLECTION to an existing collection or alias".to_string()),
+                ));
+                checks.extend(later.map(skipped));
+                return checks;
+            }
+            Err(e) => Err(e),
+        };
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                checks.push(check(
+                    "collection_exists",
+                    CheckStatus::Fail,
+                    format!("Could not read collection '{}': {}", collection, e),
+                    Some("Check QDRANT_URL and QDRANT_API_KEY and that Qdrant is reachable".to_string()),
+                ));
+                checks.extend(later.map(skipped));
+                return checks;
+            }
+        };
+        let detail = match info.alias {
+            Some(ref alias) => format!("Alias '{}' points to collection '{}' ({})", alias, info.collection, info.status),
+            None => format!("Collection '{}' exists ({})", info.collection, info.status),
+        };
+        checks.push(check("collection_exists", CheckStatus::Pass, detail, None));
+        
+        let expected = self.search.vector_size();
+        let content_vector = info.vectors
+            .iter()
+            .find(|v| v.name == VectorName::Content.as_str())
+            .or_else(|| info.vectors.iter().find(|v| v.name.is_empty()));
+        checks.push(match content_vector {
+            None => check(
+                "vector_size",
+                CheckStatus::Fail,
+                format!("Collection '{}' has no content vector", info.collection),
+                Some("Migrate the memories into a new collection with rag_migrate_model".to_string()),
+            ),
+            Some(vector) if vector.size != expected => check(
+                "vector_size",
+                CheckStatus::Fail,
+                format!(
+                    "Collection stores {}-dimensional vectors but {} produces {}",
+                    vector.size, self.config.openai.model, expected
+                ),
+                Some(format!(
+                    "Set EMBEDDING_MODEL back to the model the collection was built with, or re-embed into a new collection with rag_migrate_model (model {})",
+                    self.config.openai.model
+                )),
+            ),
+            Some(_) if self.config.openai.dimension != expected => check(
+                "vector_size",
+                CheckStatus::Warn,
+                format!(
+                    "Collection and {} agree on {} dimensions, but EMBEDDING_DIMENSION is {}; it is used when the dimension probe fails",
+                    self.config.openai.model, expected, self.config.openai.dimension
+                ),
+                Some(format!("Set EMBEDDING_DIMENSION={}", expected)),
+            ),
+            Some(vector) if vector.name.is_empty() => check(
+                "vector_size",
+                CheckStatus::Warn,
+                format!(
+                    "Collection stores {}-dimensional vectors as a single unnamed vector; summary search is unavailable",
+                    expected
+                ),
+                Some("Migrate into a new collection with rag_migrate_model to get named content and summary vectors".to_string()),
+            ),
+            Some(vector) => check(
+                "vector_size",
+                CheckStatus::Pass,
+                format!("Content vector has {} dimensions ({})", vector.size, vector.distance),
+                None,
+            ),
         });
         
-        let content = Content::json(result)
-            .map_err(|e| ErrorData::internal_error(format!("Failed to create JSON content: {}", e), None))?;
-        Ok(CallToolResult::success(vec![content]))
+        let unindexed: Vec<&str> = info.filter_fields_indexed
+            .iter()
+            .filter(|(_, indexed)| !**indexed)
+            .map(|(field, _)| field.as_str())
+            .collect();
+        checks.push(if unindexed.is_empty() {
+            check(
+                "payload_indexes",
+                CheckStatus::Pass,
+                format!("All {} filtered payload fields are indexed", info.filter_fields_indexed.len()),
+                None,
+            )
+        } else {
+            check(
+                "payload_indexes",
+                CheckStatus::Warn,
+                format!("Filtered payload fields without an index: {}; filters on them scan every point", unindexed.join(", ")),
+                Some(format!(
+                    "Create a keyword index for each field with PUT /collections/{}/index and {{\"field_name\": \"<field>\", \"field_schema\": \"keyword\"}}",
+                    info.collection
+                )),
+            )
+        });
+        
+        checks.push(match self.search.sample_payload_errors(VALIDATE_SAMPLE_POINTS).await {
+            Ok((0, _)) => check(
+                "sample_payloads",
+                CheckStatus::Skipped,
+                "The collection is empty".to_string(),
+                None,
+            ),
+            Ok((sampled, errors)) if errors.is_empty() => check(
+                "sample_payloads",
+                CheckStatus::Pass,
+                format!("{} sampled payloads deserialize as memories", sampled),
+                None,
+            ),
+            Ok((sampled, errors)) => {
+                let examples: Vec<String> = errors
+                    .iter()
+                    .take(3)
+                    .map(|(id, e)| format!("{}: {}", id, e))
+                    .collect();
+                check(
+                    "sample_payloads",
+                    CheckStatus::Fail,
+                    format!("{} of {} sampled payloads are not valid memories ({})", errors.len(), sampled, examples.join("; ")),
+                    Some(format!(
+                        "These points are skipped when listing, exporting or migrating; store their content again with rag_store and delete the old points with POST /collections/{}/points/delete",
+                        info.collection
+                    )),
+                )
+            }
+            Err(e) => check(
+                "sample_payloads",
+                CheckStatus::Fail,
+                format!("Could not scroll the collection: {}", e),
+                Some("Check that Qdrant is reachable and the API key may read points".to_string()),
+            ),
+        });
+        checks
     }
-}
-
-#[tool_handler]
-impl ServerHandler for UnifiedRagService {
-    fn get_info(&self) -> rmcp::model::ServerInfo {
-        rmcp::model::ServerInfo {
-            protocol_version: rmcp::model::ProtocolVersion::V_2024_11_05,
-            server_info: rmcp::model::Implementation {
-                name: "unified-rag".into(),
-                version: env!("CARGO_PKG_VERSION").into(),
-            },
-            capabilities: rmcp::model::ServerCapabilities {
-                tools: Some(Default::default()),
-                ..Default::default()
-            },
-            instructions: Some("UnifiedRAG MCP Server - Hybrid search with Redis cache and Qdrant semantic search".into()),
+    
+    /// This service as seen by another instance for one call: its Redis key prefix,
+    /// and the `instance_id` stored memories get. `None` or the server's own id
+    /// is the server itself; other ids are checked like `INSTANCE_ID`, but rejected.
+    fn scoped(&self, instance: Option<&str>) -> std::result::Result<Cow<'_, Self>, ErrorData> {
+        let Some(instance) = instance.map(str::trim).filter(|i| *i != self.instance_id) else {
+            return Ok(Cow::Borrowed(self));
+        };
+        validate_instance_id(&self.config.redis.key_scheme, instance)?;
+        
+        let mut scoped = self.clone();
+        scoped.cache = Arc::new(self.cache.for_instance(instance));
+        scoped.instance_id = instance.to_string();
+        Ok(Cow::Owned(scoped))
+    }
+    
+    /// Qdrant instance filter for a search: an explicit `instance_filter` as given, none
+    /// across all instances, and otherwise this (possibly overridden) instance, the
+    /// same boundary the cache side keeps
+    fn instance_filter(&self, instance_filter: Option<Vec<String>>, all_instances: bool) -> Option<Vec<String>> {
+        match instance_filter {
+            Some(instances) => Some(instances),
+            None if all_instances => None,
+            None => Some(vec![self.instance_id.clone()]),
         }
     }
-}
\ No newline at end of file
+    
+    /// Reject searches reaching past `MAX_SEARCH_WINDOW` results, before the window is
+    /// multiplied into a candidate count
+    fn check_search_window(&self, window: usize) -> std::result::Result<(), ErrorData> {
+        if window > self.config.max_search_window {
+            return Err(invalid_input(format!(
+                "offset + limit is {} but MAX_SEARCH_WINDOW allows at most {}",
+                window, self.config.max_search_window
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Whether a failed embedding during a store is deferred instead of failing it;
+    /// only possible when the memory can be kept in Redis meanwhile, and only for the
+    /// server's own instance, whose queue the background task drains
+    fn defers_embeddings(&self) -> bool {
+        self.config.openai.defer_failed_embeddings
+            && self.config.storage_mode.stores_in_redis()
+            && self.instance_id == self.config.instance_id
+    }
+    
+    /// Whether a store's embedding input is under `MIN_EMBED_CHARS` and so kept out of
+    /// the vector space; it then lives in Redis only, so `qdrant_primary` embeds it anyway
+    fn below_min_embed_chars(&self, request: &StoreRequest) -> bool {
+        let Some(min) = self.config.openai.min_embed_chars else {
+            return false;
+        };
+        let prepared = text::preprocess(&request.content, request.strip_markdown, request.strip_html);
+        self.config.storage_mode.stores_in_redis() && prepared.trim().chars().count() < min
+    }
+    
+    /// Background loop retrying deferred embeddings every `EMBEDDING_RETRY_INTERVAL_SECS`.
+    /// Does nothing unless `DEFER_FAILED_EMBEDDINGS` is set.
+    pub async fn run_embedding_queue(self) {
+        if !self.defers_embeddings() {
+            return;
+        }
+        
+        let interval = std::time::Duration::from_secs(self.config.openai.embedding_retry_interval_secs);
+        loop {
+            tokio::time::sleep(interval).await;
+            match self.drain_embedding_queue().await {
+                Ok(0) => {}
+                Ok(indexed) => tracing::info!("Indexed {} memories with deferred embeddings", indexed),
+                Err(e) => tracing::warn!("Deferred embeddings still pending: {}", e),
+            }
+        }
+    }
+    
+    /// Embed and index queued memories oldest first, returning how many were indexed.
+    /// Stops at the first failure, since the embedding API or Qdrant is likely still
+    /// down; the rest wait for the next pass. Memories deleted meanwhile are dropped.
+    async fn drain_embedding_queue(&self) -> crate::error::Result<usize> {
+        let mut indexed = 0;
+        loop {
+            let ids = self.cache.queued_embeddings(EMBEDDING_QUEUE_BATCH).await?;
+            if ids.is_empty() {
+                return Ok(indexed);
+            }
+            
+            let keys: Vec<(String, String)> = ids.iter().map(|id| (self.instance_id.clone(), id.clone())).collect();
+            let memories = self.cache.get_many(&keys).await?;
+            let mut ready_ids = Vec::with_capacity(ids.len());
+            let mut ready = Vec::with_capacity(ids.len());
+            let mut failure = None;
+            for (id, memory) in ids.iter().zip(memories) {
+                let Some(mut memory) = memory else {
+                    tracing::warn!("Deferred memory {} is gone from Redis; dropping it from the embedding queue", id);
+                    self.cache.dequeue_embedding(id).await?;
+                    continue;
+                };
+                
+                // Stored content already fits, or was stored with truncate_long_content.
+                // Per-store stripping isn't queued, so the global settings apply.
+                let prepared = self.embedding_text(&memory.content);
+                let input = text::truncate_to_tokens(&prepared, self.config.openai.max_content_tokens);
+                match self.embed(input).await {
+                    Ok(embedding) => memory.embedding = Some(embedding),
+                    Err(e) => {
+                        failure = Some(e);
+                        break;
+                    }
+                }
+                ready_ids.push(id);
+                ready.push(memory);
+            }
+            
+            // Whatever was embedded before a failure is still indexed in one go
+            self.search.index_many(&ready).await?;
+            for id in ready_ids {
+                self.cache.dequeue_embedding(id).await?;
+                indexed += 1;
+            }
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+    }
+    
+    /// Store content too long to embed in one piece as a chain of chunks, each
+    /// within `MAX_CONTENT_TOKENS`. The result describes the first chunk and lists all ids.
+    async fn store_chunked(&self, request: StoreRequest, source: &str) -> crate::error::Result<StoreResult> {
+        let chain_id = request.chain_id.clone().unwrap_or_else(|| format!("chunked:{}", Uuid::new_v4()));
+        let chunks = text::chunk_by_tokens(&request.content, self.config.openai.max_content_tokens);
+        
+        let mut first: Option<StoreResult> = None;
+        let mut chunk_ids = Vec::with_capacity(chunks.len());
+        let mut timings = StoreTimings::default();
+        for chunk in chunks {
+            // Later chunks would only link to their predecessors
+            let chunk_request = StoreRequest {
+                content: chunk,
+                chain_id: Some(chain_id.clone()),
+                embedding: None,
+                auto_link: request.auto_link && first.is_none(),
+                ..request.clone()
+            };
+            let result = self.store_memory(chunk_request, source).await?;
+            chunk_ids.push(result.memory_id);
+            if let Some(ref chunk) = result.timings {
+                timings.embedding_ms += chunk.embedding_ms;
+                timings.redis_ms += chunk.redis_ms;
+                timings.qdrant_ms += chunk.qdrant_ms;
+                timings.total_ms += chunk.total_ms;
+            }
+            first.get_or_insert(result);
+        }
+        
+        let mut result = first.ok_or_else(|| UnifiedRagError::ToolError("content has no chunks to store".to_string()))?;
+        result.chunk_ids = chunk_ids;
+        result.timings = Some(timings);
+        Ok(result)
+    }
+    
+    /// This instance's closest memory by content vector, if its cosine score reaches
+    /// `threshold`
+    async fn nearest_memory(&self, content: &str, embedding: &[f32], threshold: f32) -> crate::error::Result<Option<Uuid>> {
+        let request = SearchRequest {
+            query: content.to_string(),
+            limit: Some(1),
+            threshold: None,
+            category_filter: None,
+            category_match_mode: CategoryMatchMode::Exact,
+            tags_filter: None,
+            tag_min_match: None,
+            instance_filter: Some(vec![self.instance_id.clone()]),
+            chain_filter: None,
+            extra_filters: None,
+            mode: SearchMode::SemanticOnly,
+            vector: VectorName::Content,
+            bypass_cache: false,
+            cache_ttl: None,
+            hnsw_ef: None,
+            all_instances: false,
+            exclude_ids: Vec::new(),
+            text_match: None,
+            model: None,
+        };
+        
+        let nearest = self.search.search_with_embedding(&request, embedding.to_vec()).await?;
+        Ok(nearest
+            .memories
+            .into_iter()
+            .find(|m| m.relevance_score >= threshold)
+            .map(|m| m.id))
+    }
+    
+    /// Ids on this instance's chain `chain_id` in Redis and in Qdrant; in
+    /// `qdrant_primary` mode Redis has no chains, and Qdrant can hold stragglers
+    async fn chain_ids(&self, chain_id: &str) -> crate::error::Result<Vec<String>> {
+        let mut ids = if self.config.storage_mode.stores_in_redis() {
+            self.cache.chain_members(chain_id).await?
+        } else {
+            Vec::new()
+        };
+        for id in self.search.chain_ids(&self.instance_id, chain_id).await? {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+    
+    /// Delete a memory from both stores, ignoring ones that are already gone
+    async fn remove_memory(&self, id: &str) {
+        if let Err(e) = self.cache.invalidate(id).await {
+            tracing::warn!("Failed to remove memory {} from Redis: {}", id, e);
+        }
+        if let Err(e) = self.search.delete(id).await {
+            tracing::warn!("Failed to remove memory {} from Qdrant: {}", id, e);
+        }
+        if let Err(e) = self.cache.drop_relations(id).await {
+            tracing::warn!("Failed to remove links of memory {}: {}", id, e);
+        }
+    }
+    
+    /// Keyword scan of the Redis cache: this instance's thoughts, or every registered
+    /// instance's when `all_instances` is set. An instance that fails to scan is skipped.
+    async fn cache_search(&self, request: &SearchRequest) -> crate::error::Result<CachedSearch> {
+        if !request.all_instances {
+            return self.cache.search_cached(request).await;
+        }
+        
+        let mut combined = CachedSearch::default();
+        for instance in self.cache.instances().await? {
+            match self.cache.for_instance(&instance.instance_id).search_cached(request).await {
+                Ok(found) => {
+                    combined.memories.extend(found.memories);
+                    combined.truncated |= found.truncated;
+                }
+                Err(e) => tracing::warn!("Cache search of instance {} failed: {}", instance.instance_id, e),
+            }
+        }
+        Ok(combined)
+    }
+    
+    /// Load a memory from this instance's Redis, falling back to Qdrant for memories
+    /// only indexed there. The flag tells whether Redis had it. Qdrant holds every
+    /// instance's points, so another instance's memory is reported as not found.
+    async fn find_memory(&self, id: &str) -> std::result::Result<(Memory, bool), ErrorData> {
+        let cached = self.cache.get(id).await.map_err(|e| {
+            internal_error(format!("Failed to read memory {} from Redis: {}", id, e), e.code())
+        })?;
+        if let Some(memory) = cached {
+            return Ok((memory, true));
+        }
+        
+        // Qdrant payloads carry no content in redis_primary mode
+        if !self.config.storage_mode.full_qdrant_payload() {
+            return Err(invalid_input(format!("Memory {} not found", id)));
+        }
+        match self.search.get_memory(id).await.map_err(search_error)? {
+            Some(memory) if memory.instance_id == self.instance_id => Ok((memory, false)),
+            _ => Err(invalid_input(format!("Memory {} not found", id))),
+        }
+    }
+    
+    /// Semantic search against Qdrant using the cached query embedding
+    async fn semantic_search(&self, request: &SearchRequest) -> crate::error::Result<SearchResult> {
+        let start = Instant::now();
+        let embedding = self.embed_with(request.model.as_deref(), &request.query).await?;
+        let embedding_ms = elapsed_ms(start);
+        
+        let start = Instant::now();
+        let mut result = self.search.search_with_embedding(request, embedding).await?;
+        let qdrant_ms = elapsed_ms(start);
+        
+        let start = Instant::now();
+        result.memories = self.hydrate(result.memories).await?;
+        result.timings = Some(SearchTimings {
+            embedding_ms,
+            qdrant_ms,
+            hydrate_ms: elapsed_ms(start),
+            ..SearchTimings::default()
+        });
+        Ok(result)
+    }
+    
+    /// In `redis_primary` mode Qdrant hits carry only ids and filter metadata; swap
+    /// in the full memories from Redis with one MGET, keeping Qdrant's scores. Hits
+    /// missing from Redis are dropped. Other modes pass results through.
+    async fn hydrate(&self, memories: Vec<Memory>) -> crate::error::Result<Vec<Memory>> {
+        if self.config.storage_mode.full_qdrant_payload() || memories.is_empty() {
+            return Ok(memories);
+        }
+        
+        let ids: Vec<(String, String)> = memories
+            .iter()
+            .map(|m| (m.instance_id.clone(), m.id.to_string()))
+            .collect();
+        let full = self.cache.get_many(&ids).await?;
+        
+        Ok(memories
+            .into_iter()
+            .zip(full)
+            .filter_map(|(hit, full)| match full {
+                Some(mut memory) => {
+                    memory.relevance_score = hit.relevance_score;
+                    Some(memory)
+                }
+                None => {
+                    tracing::warn!("Memory {} is indexed in Qdrant but missing from Redis", hit.id);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Learn the embedding dimension from the model itself, so the collection can't be
+/// created with a size the model doesn't produce. The result is remembered in Redis
+/// per model; if the probe fails, the configured `EMBEDDING_DIMENSION` is used.
+async fn detect_dimension(cache: &RedisCache, embeddings: &EmbeddingGenerator, fallback: u64) -> u64 {
+    let model = embeddings.model();
+    if let Ok(Some(dimension)) = cache.embedding_dimension(model).await {
+        tracing::info!("Embedding dimension for {}: {} (cached)", model, dimension);
+        return dimension;
+    }
+    if !embeddings.is_available() {
+        tracing::info!("Embedding dimension for {}: {} (configured; no API key to probe with)", model, fallback);
+        return fallback;
+    }
+    
+    match embeddings.probe_dimension().await {
+        Ok(dimension) => {
+            tracing::info!("Embedding dimension for {}: {} (probed)", model, dimension);
+            if let Err(e) = cache.set_embedding_dimension(model, dimension).await {
+                tracing::warn!("Failed to cache embedding dimension: {}", e);
+            }
+            dimension
+        }
+        Err(e) => {
+            tracing::warn!("Embedding dimension probe failed ({}), using configured {}", e, fallback);
+            fallback
+        }
+    }
+}
+
+/// Milliseconds since `start`, for `SearchTimings`
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Tool failure carrying a stable `code` in its data for clients to branch on
+fn internal_error(message: impl Into<Cow<'static, str>>, code: &str) -> ErrorData {
+    ErrorData::internal_error(message, Some(serde_json::json!({ "code": code })))
+}
+
+/// A per-call instance id (an `instance` override or a `rag_move` target), checked
+/// like `INSTANCE_ID` but rejected instead of only logged
+fn validate_instance_id(keys: &KeyScheme, instance: &str) -> std::result::Result<(), ErrorData> {
+    match keys.instance_id_problem(instance) {
+        Some(problem) => Err(invalid_input(problem)),
+        None => Ok(()),
+    }
+}
+
+/// `search_all_instances` and `instance_filter` ask for opposite scopes
+fn validate_instance_scope(instance_filter: Option<&Vec<String>>, all_instances: bool) -> std::result::Result<(), ErrorData> {
+    if all_instances && instance_filter.is_some() {
+        return Err(invalid_input("search_all_instances cannot be combined with instance_filter"));
+    }
+    Ok(())
+}
+
+fn validate_search_ef(search_ef: Option<u64>) -> std::result::Result<(), ErrorData> {
+    if search_ef == Some(0) {
+        return Err(invalid_input("search_ef must be at least 1"));
+    }
+    Ok(())
+}
+
+/// `exclude_ids` as UUIDs, rejecting the first malformed entry
+fn parse_exclude_ids(ids: &[String]) -> std::result::Result<Vec<Uuid>, ErrorData> {
+    ids.iter()
+        .map(|id| Uuid::parse_str(id).map_err(|e| invalid_input(format!("Invalid exclude_ids entry {:?}: {}", id, e))))
+        .collect()
+}
+
+/// A per-call embedding model override, trimmed; blank means the default
+fn embedding_model_param(model: Option<&str>) -> std::result::Result<Option<String>, ErrorData> {
+    match model.map(str::trim) {
+        Some(model) if model.chars().any(|c| c.is_whitespace() || c.is_control()) => {
+            Err(invalid_input(format!("Invalid model {:?}", model)))
+        }
+        Some(model) if !model.is_empty() => Ok(Some(model.to_string())),
+        _ => Ok(None),
+    }
+}
+
+/// The summary vector is always embedded with `EMBEDDING_MODEL`, so a store with
+/// another `model` can't also carry a summary on a collection with named vectors:
+/// its two vectors would come from different embedding spaces
+fn validate_summary_model(
+    model: Option<&str>,
+    default_model: &str,
+    summary: bool,
+    named_vectors: bool,
+) -> std::result::Result<(), ErrorData> {
+    match model {
+        Some(model) if model != default_model && summary && named_vectors => Err(invalid_input(format!(
+            "model {} can't be combined with summary: the summary vector is embedded with EMBEDDING_MODEL ({})",
+            model, default_model
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Rejected tool arguments, tagged `INVALID_INPUT`
+fn invalid_input(message: impl Into<Cow<'static, str>>) -> ErrorData {
+    ErrorData::invalid_params(message, Some(serde_json::json!({ "code": INVALID_INPUT })))
+}
+
+fn search_error(e: UnifiedRagError) -> ErrorData {
+    tracing::error!("Search failed: {}", e);
+    if let UnifiedRagError::EmbeddingsUnavailable(_) = e {
+        return internal_error(format!("Search failed: {}", e), e.code());
+    }
+    internal_error(
+        format!("Search failed: {}. Please check that Qdrant is running and accessible.", e),
+        e.code(),
+    )
+}
+
+/// Implementation of MCP tools using rmcp macros
+#[tool_router]
+impl UnifiedRagService {
+    /// Search for memories using hybrid L1/L2 retrieval
+    #[tool(description = "Search for memories using hybrid L1/L2 retrieval with Redis caching and Qdrant semantic search")]
+    pub async fn rag_search(
+        &self,
+        params: Parameters<RagSearchParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_search")?;
+        let started = Instant::now();
+        let hybrid_alpha = params.0.hybrid_alpha;
+        if !(0.0..=1.0).contains(&hybrid_alpha) {
+            return Err(invalid_input("hybrid_alpha must be between 0.0 and 1.0"));
+        }
+        validate_filters(
+            params.0.category_filter.as_deref(),
+            params.0.category_match_mode,
+            params.0.tags_filter.as_deref(),
+            params.0.tag_min_match,
+        )?;
+        validate_instance_scope(params.0.instance_filter.as_ref(), params.0.search_all_instances)?;
+        if params.0.recency_half_life_days.is_some_and(|days| !(days.is_finite() && days > 0.0)) {
+            return Err(invalid_input("recency_half_life_days must be a positive number"));
+        }
+        if params.0.max_content_chars == Some(0) {
+            return Err(invalid_input("max_content_chars must be at least 1"));
+        }
+        validate_search_ef(params.0.search_ef)?;
+        validate_extra_filters(params.0.extra_filters.as_ref())?;
+        let exclude_ids = parse_exclude_ids(&params.0.exclude_ids)?;
+        let mode = params.0.mode.unwrap_or(if params.0.hybrid_mode { SearchMode::Hybrid } else { SearchMode::SemanticOnly });
+        if mode == SearchMode::CacheOnly && !self.config.storage_mode.stores_in_redis() {
+            return Err(invalid_input("mode cache_only needs memories in Redis, which STORAGE_MODE=qdrant_primary doesn't write"));
+        }
+        let text_match = params.0.text_match.clone().filter(|text| !text.trim().is_empty());
+        let model = embedding_model_param(params.0.model.as_deref())?;
+        if text_match.is_some() && mode != SearchMode::CacheOnly && !self.config.storage_mode.full_qdrant_payload() {
+            return Err(invalid_input(
+                "text_match needs content in Qdrant payloads, which STORAGE_MODE=redis_primary leaves out; use mode cache_only",
+            ));
+        }
+        let scoped = self.scoped(params.0.instance.as_deref())?;
+        
+        let reranker = match (params.0.rerank, &self.reranker) {
+            (false, _) => None,
+            (true, Some(reranker)) => Some(reranker.clone()),
+            (true, None) => {
+                return Err(invalid_input("rerank requested but RERANK_URL is not configured"));
+            }
+        };
+        
+        self.check_vector_available(params.0.vector)?;
+        
+        // Over-fetch candidates so dropped results (missing from Redis, duplicates across
+        // instances) don't shrink the page, and more still for the reranker to choose from.
+        // Pages past the first are cut from the top `offset + limit` results.
+        let offset = params.0.offset;
+        let limit = offset.saturating_add(params.0.limit);
+        self.check_search_window(limit)?;
+        let multiplier = match reranker {
+            Some(_) => self.config.rerank.candidate_multiplier.max(self.config.search_candidate_multiplier),
+            None => self.config.search_candidate_multiplier,
+        };
+        let candidates = limit.saturating_mul(multiplier);
+        
+        let request = SearchRequest {
+            // Normalize so cache keys and embeddings agree across NFC/NFD input
+            query: text::normalize(&params.0.query).into_owned(),
+            limit: Some(candidates),
+            threshold: Some(params.0.threshold.unwrap_or(self.config.similarity_threshold)),
+            category_filter: params.0.category_filter,
+            category_match_mode: params.0.category_match_mode,
+            tags_filter: params.0.tags_filter,
+            tag_min_match: params.0.tag_min_match,
+            instance_filter: scoped.instance_filter(params.0.instance_filter, params.0.search_all_instances),
+            chain_filter: params.0.chain_filter,
+            extra_filters: params.0.extra_filters,
+            mode,
+            vector: params.0.vector,
+            bypass_cache: params.0.bypass_cache,
+            cache_ttl: params.0.cache_ttl,
+            hnsw_ef: params.0.search_ef,
+            all_instances: params.0.search_all_instances,
+            exclude_ids,
+            text_match,
+            model,
+        };
+        
+        let mut result = match request.mode {
+            SearchMode::Hybrid => {
+                // Query both layers concurrently and merge; if one fails, use the other's results
+                let timed_cache_search = async {
+                    let start = Instant::now();
+                    (scoped.cache_search(&request).await, elapsed_ms(start))
+                };
+                let ((cache_outcome, cache_ms), semantic_outcome) = tokio::join!(
+                    timed_cache_search,
+                    scoped.semantic_search(&request),
+                );
+                
+                let (cached, mut search_result) = match (cache_outcome, semantic_outcome) {
+                    (Ok(cached), Ok(search_result)) => (cached, search_result),
+                    (Err(e), Ok(mut search_result)) => {
+                        tracing::warn!("Cache search failed, using semantic results only: {}", e);
+                        search_result.partial = true;
+                        search_result.warnings.push(format!("Redis cache search failed: {}", e));
+                        (CachedSearch::default(), search_result)
+                    }
+                    (Ok(cached), Err(e)) => {
+                        tracing::warn!("Semantic search failed, using cache results only: {}", e);
+                        let search_result = SearchResult {
+                            memories: Vec::new(),
+                            search_id: Uuid::new_v4(),
+                            query_embedding: None,
+                            cache_hits: 0,
+                            total_results: 0,
+                            search_time_ms: 0,
+                            partial: true,
+                            warnings: vec![format!("Qdrant semantic search failed: {}", e)],
+                            timings: None,
+                            truncated: false,
+                            response_truncated: false,
+                            next_offset: None,
+                        };
+                        (cached, search_result)
+                    }
+                    (Err(cache_error), Err(e)) => {
+                        tracing::warn!("Cache search failed: {}", cache_error);
+                        return Err(search_error(e));
+                    }
+                };
+                
+                search_result.truncated = cached.truncated;
+                let cache_results = cached.memories;
+                search_result.cache_hits = cache_results.len();
+                search_result.timings.get_or_insert_with(SearchTimings::default).cache_ms = cache_ms;
+                search_result.memories = fusion::fuse(
+                    &request.query,
+                    cache_results,
+                    search_result.memories,
+                    hybrid_alpha,
+                    candidates,
+                );
+                search_result
+            }
+            // Direct Qdrant search
+            SearchMode::SemanticOnly => scoped.semantic_search(&request).await.map_err(search_error)?,
+            SearchMode::CacheOnly => {
+                // Nothing is embedded: results are ranked by keyword overlap alone
+                let start = Instant::now();
+                let cached = scoped.cache_search(&request).await.map_err(|e| {
+                    tracing::error!("Cache search failed: {}", e);
+                    internal_error(format!("Cache search failed: {}", e), e.code())
+                })?;
+                let cache_ms = elapsed_ms(start);
+                SearchResult {
+                    cache_hits: cached.memories.len(),
+                    memories: fusion::fuse(&request.query, cached.memories, Vec::new(), 0.0, candidates),
+                    search_id: Uuid::new_v4(),
+                    query_embedding: None,
+                    total_results: 0,
+                    search_time_ms: 0,
+                    partial: false,
+                    warnings: Vec::new(),
+                    timings: Some(SearchTimings { cache_ms, ..SearchTimings::default() }),
+                    truncated: cached.truncated,
+                    response_truncated: false,
+                    next_offset: None,
+                }
+            }
+        };
+        
+        // The same text stored by several instances should only show up once
+        if request.all_instances {
+            result.memories = fusion::dedup_by_content(std::mem::take(&mut result.memories));
+        }
+        
+        let mut timings = result.timings.take().unwrap_or_default();
+        if let Some(reranker) = reranker {
+            let candidates = std::mem::take(&mut result.memories);
+            let start = Instant::now();
+            let reranked = reranker.rerank(&request.query, candidates.clone(), limit).await;
+            timings.rerank_ms = elapsed_ms(start);
+            match reranked {
+                Ok(reranked) => result.memories = reranked,
+                Err(e) => {
+                    // Fall back to the fused order rather than failing the whole search
+                    tracing::warn!("Rerank failed, returning unreranked results: {}", e);
+                    result.memories = candidates;
+                    result.memories.truncate(limit);
+                    result.partial = true;
+                    result.warnings.push(format!("Rerank failed, results are not reranked: {}", e));
+                }
+            }
+        }
+        if let Some(half_life_days) = params.0.recency_half_life_days {
+            fusion::apply_recency_decay(&mut result.memories, half_life_days, Utc::now());
+        }
+        // Qdrant and the reranker order equal scores arbitrarily; pages must not shift
+        result.memories.sort_by(fusion::rank_order);
+        result.memories.truncate(limit);
+        result.memories.drain(..offset.min(result.memories.len()));
+        result.total_results = result.memories.len();
+        if params.0.timings {
+            timings.total_ms = elapsed_ms(started);
+            result.timings = Some(timings);
+        }
+        for memory in &mut result.memories {
+            memory.filter_match = request.filter_match(memory);
+        }
+        
+        // Offsets refer to the full content, so pick snippets before any truncation
+        if params.0.snippets {
+            for memory in &mut result.memories {
+                memory.snippet = text::best_sentence(&request.query, &memory.content).map(|(start, end)| Snippet {
+                    text: memory.content[start..end].to_string(),
+                    start: memory.content[..start].chars().count(),
+                    end: memory.content[..end].chars().count(),
+                });
+            }
+        }
+        
+        // Only the response is shortened; stored and cached copies keep the full text
+        if let Some(max_chars) = params.0.max_content_chars {
+            for memory in &mut result.memories {
+                if let Some(short) = text::truncate(&memory.content, max_chars) {
+                    memory.content = short;
+                    memory.truncated = true;
+                }
+            }
+        }
+        if let Some(max_bytes) = self.config.max_response_bytes {
+            fit_response(&mut result, offset, max_bytes);
+        }
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Semantic search with a caller-supplied query vector
+    #[tool(description = "Search Qdrant with a query vector you already have, skipping embedding generation. The vector length must match the collection's embedding dimension; filters and threshold work as in rag_search")]
+    pub async fn rag_search_by_embedding(
+        &self,
+        params: Parameters<RagSearchByEmbeddingParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_search_by_embedding")?;
+        let params = params.0;
+        
+        let dimension = self.search.vector_size() as usize;
+        if params.embedding.len() != dimension {
+            return Err(invalid_input(
+                format!("embedding has {} dimensions but the collection expects {}", params.embedding.len(), dimension),
+            ));
+        }
+        if params.embedding.iter().any(|v| !v.is_finite()) {
+            return Err(invalid_input("embedding must contain only finite numbers"));
+        }
+        validate_filters(
+            params.category_filter.as_deref(),
+            params.category_match_mode,
+            params.tags_filter.as_deref(),
+            params.tag_min_match,
+        )?;
+        validate_instance_scope(params.instance_filter.as_ref(), params.search_all_instances)?;
+        self.check_search_window(params.limit)?;
+        validate_search_ef(params.search_ef)?;
+        validate_extra_filters(params.extra_filters.as_ref())?;
+        let exclude_ids = parse_exclude_ids(&params.exclude_ids)?;
+        let scoped = self.scoped(params.instance.as_deref())?;
+        self.check_vector_available(params.vector)?;
+        
+        let request = SearchRequest {
+            query: String::new(),
+            limit: Some(params.limit),
+            threshold: Some(params.threshold.unwrap_or(self.config.similarity_threshold)),
+            category_filter: params.category_filter,
+            category_match_mode: params.category_match_mode,
+            tags_filter: params.tags_filter,
+            tag_min_match: params.tag_min_match,
+            instance_filter: scoped.instance_filter(params.instance_filter, params.search_all_instances),
+            chain_filter: params.chain_filter,
+            extra_filters: params.extra_filters,
+            mode: SearchMode::SemanticOnly,
+            vector: params.vector,
+            bypass_cache: false,
+            cache_ttl: None,
+            hnsw_ef: params.search_ef,
+            all_instances: params.search_all_instances,
+            exclude_ids,
+            text_match: None,
+            model: None,
+        };
+        
+        let mut result = self.search
+            .search_with_embedding(&request, params.embedding)
+            .await
+            .map_err(search_error)?;
+        result.memories = scoped.hydrate(result.memories).await.map_err(|e| {
+            internal_error(format!("Failed to load memories from Redis: {}", e), e.code())
+        })?;
+        if request.all_instances {
+            result.memories = fusion::dedup_by_content(std::mem::take(&mut result.memories));
+        }
+        // Qdrant orders equal scores arbitrarily; repeated calls must agree
+        result.memories.sort_by(fusion::rank_order);
+        result.memories.truncate(params.limit);
+        result.total_results = result.memories.len();
+        // The caller already has the vector; don't echo it back
+        result.query_embedding = None;
+        for memory in &mut result.memories {
+            memory.filter_match = request.filter_match(memory);
+        }
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Load several memories by id in one call
+    #[tool(description = "Load up to 100 memories by id in one call: one Redis MGET, then a single Qdrant lookup for ids Redis doesn't have. Returns the memories in request order, with null for ids that weren't found; embeddings are left out")]
+    pub async fn rag_get_many(
+        &self,
+        params: Parameters<RagGetManyParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_get_many")?;
+        let ids = params.0.ids;
+        if ids.is_empty() {
+            return Err(invalid_input("ids must name at least one memory"));
+        }
+        if ids.len() > GET_MANY_MAX_IDS {
+            return Err(invalid_input(format!("ids names {} memories, at most {} are allowed", ids.len(), GET_MANY_MAX_IDS)));
+        }
+        let ids = ids
+            .iter()
+            .map(|id| {
+                Uuid::parse_str(id)
+                    .map(|u| u.to_string())
+                    .map_err(|e| invalid_input(format!("Invalid id {}: {}", id, e)))
+            })
+            .collect::<std::result::Result<Vec<String>, ErrorData>>()?;
+        
+        let mut memories = if self.config.storage_mode.stores_in_redis() {
+            let keys: Vec<(String, String)> = ids.iter().map(|id| (self.instance_id.clone(), id.clone())).collect();
+            self.cache.get_many(&keys).await.map_err(|e| {
+                internal_error(format!("Failed to read memories from Redis: {}", e), e.code())
+            })?
+        } else {
+            vec![None; ids.len()]
+        };
+        
+        // Qdrant payloads carry no content in redis_primary mode
+        if self.config.storage_mode.full_qdrant_payload() {
+            let mut missing: Vec<String> = Vec::new();
+            for (id, memory) in ids.iter().zip(&memories) {
+                if memory.is_none() && !missing.contains(id) {
+                    missing.push(id.clone());
+                }
+            }
+            // The collection holds every instance's points; only this one's are returned
+            let found: Vec<Memory> = self.search
+                .get_memories(&missing)
+                .await
+                .map_err(search_error)?
+                .into_iter()
+                .filter(|m| m.instance_id == self.instance_id)
+                .collect();
+            for (slot, id) in memories.iter_mut().zip(&ids) {
+                if slot.is_none() {
+                    *slot = found.iter().find(|m| m.id.to_string() == *id).cloned();
+                }
+            }
+        }
+        
+        for memory in memories.iter_mut().flatten() {
+            memory.embedding = None;
+        }
+        let result = GetManyResult {
+            found: memories.iter().filter(|m| m.is_some()).count(),
+            memories,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Store a memory with automatic embedding generation
+    #[tool(description = "Store a memory with automatic embedding generation and indexing in both Redis and Qdrant")]
+    pub async fn rag_store(
+        &self,
+        params: Parameters<RagStoreParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_store")?;
+        let scoped = self.scoped(params.0.instance.as_deref())?;
+        let result = scoped.store_params(params.0).await?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Store a memory and return the existing memories closest to it
+    #[tool(description = "Store a memory exactly like rag_store, then return the existing memories most similar to it (by its fresh content vector, excluding itself) in the same call")]
+    pub async fn rag_store_and_relate(
+        &self,
+        params: Parameters<RagStoreAndRelateParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_store_and_relate")?;
+        let params = params.0;
+        if params.neighbors == 0 {
+            return Err(invalid_input("neighbors must be at least 1"));
+        }
+        
+        let scoped = self.scoped(params.store.instance.as_deref())?;
+        let store = scoped.store_params(params.store).await?;
+        
+        // The memory is stored at this point, so a failed lookup is reported rather than raised
+        let threshold = params.threshold.unwrap_or(self.config.similarity_threshold);
+        let mut warnings = Vec::new();
+        let neighbors = match scoped.related_memories(&store, params.neighbors, threshold).await {
+            Ok(neighbors) => neighbors,
+            Err(e) => {
+                tracing::warn!("Finding neighbors of {} failed: {}", store.memory_id, e);
+                warnings.push(format!("Stored, but finding related memories failed: {}", e));
+                Vec::new()
+            }
+        };
+        
+        let result = StoreAndRelateResult { store, neighbors, warnings };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Import markdown notes from an Obsidian vault
+    #[tool(description = "Import an Obsidian vault: walks a directory of .md files, reads tags/category from frontmatter, chunks long notes and stores each chunk with source \"obsidian\". Notes unchanged since the last import are skipped.")]
+    pub async fn rag_import_vault(
+        &self,
+        params: Parameters<RagImportVaultParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_import_vault")?;
+        let params = params.0;
+        let root = PathBuf::from(&params.path);
+        
+        let walk_root = root.clone();
+        let files = tokio::task::spawn_blocking(move || obsidian::find_markdown_files(&walk_root))
+            .await
+            .map_err(|e| internal_error(format!("Vault scan panicked: {}", e), INTERNAL_ERROR))?
+            .map_err(|e| invalid_input(format!("Cannot read vault at {}: {}", params.path, e)))?;
+        
+        let mut notes_imported = 0usize;
+        let mut notes_unchanged = 0usize;
+        let mut memories_stored = 0usize;
+        let mut failures = Vec::new();
+        
+        for file in &files {
+            let relative = file
+                .strip_prefix(&root)
+                .unwrap_or(file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            
+            let raw = match tokio::fs::read_to_string(file).await {
+                Ok(raw) => raw,
+                Err(e) => {
+                    failures.push(ImportFailure { path: relative, error: e.to_string() });
+                    continue;
+                }
+            };
+            
+            let hash = text::sha256_hex(text::normalize(&raw).as_bytes());
+            if let Ok(Some(previous)) = self.cache.import_hash("obsidian", &relative).await {
+                if previous == hash {
+                    notes_unchanged += 1;
+                    continue;
+                }
+            }
+            
+            let note = obsidian::parse_note(&raw);
+            let tags = match self.limit_tags(normalize_tags(&note.tags)) {
+                Ok((tags, _)) => tags,
+                Err(error) => {
+                    failures.push(ImportFailure { path: relative, error });
+                    continue;
+                }
+            };
+            // Chunks of one note share a chain. The previous import's chunks are only
+            // replaced once the new ones are stored, so a failed re-import keeps the note.
+            let chain_id = format!("obsidian:{}", relative);
+            let previous = match self.chain_ids(&chain_id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    failures.push(ImportFailure { path: relative, error: e.to_string() });
+                    continue;
+                }
+            };
+            
+            let mut stored = Vec::new();
+            let mut error = None;
+            for chunk in text::chunk(&note.body, params.max_chunk_chars) {
+                let request = StoreRequest {
+                    content: chunk,
+                    category: note.category.clone().or_else(|| params.category.clone()),
+                    tags: tags.clone(),
+                    importance: params.importance,
+                    chain_id: Some(chain_id.clone()),
+                    parent_id: None,
+                    framework: None,
+                    embedding: None,
+                    summary: None,
+                    dedup: false,
+                    truncate: params.truncate_long_content,
+                    auto_link: false,
+                    strip_markdown: self.config.openai.strip_markdown,
+                    strip_html: self.config.openai.strip_html,
+                    extra: BTreeMap::new(),
+                    model: None,
+                };
+                match self.store_memory(request, "obsidian").await {
+                    Ok(result) => stored.push(result.memory_id),
+                    Err(e) => {
+                        error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+            
+            match error {
+                Some(error) => {
+                    for id in &stored {
+                        self.remove_memory(&id.to_string()).await;
+                    }
+                    failures.push(ImportFailure { path: relative, error });
+                }
+                None => {
+                    for id in previous {
+                        self.remove_memory(&id).await;
+                    }
+                    memories_stored += stored.len();
+                    if let Err(e) = self.cache.set_import_hash("obsidian", &relative, &hash).await {
+                        tracing::warn!("Failed to record import hash for {}: {}", relative, e);
+                    }
+                    notes_imported += 1;
+                }
+            }
+        }
+        
+        let result = ImportVaultResult {
+            files_scanned: files.len(),
+            notes_imported,
+            notes_unchanged,
+            memories_stored,
+            failures,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Count memories per group over an optional filter
+    #[tool(description = "Count memories grouped by category, tag, framework or source, optionally filtered by category, tags, instance and creation time")]
+    pub async fn rag_aggregate(
+        &self,
+        params: Parameters<RagAggregateParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_aggregate")?;
+        let params = params.0;
+        let request = AggregateRequest {
+            group_by: params.group_by,
+            category_filter: params.category_filter,
+            tags_filter: params.tags_filter,
+            instance_filter: params.instance_filter,
+            created_after: parse_timestamp("created_after", params.created_after.as_deref())?,
+            created_before: parse_timestamp("created_before", params.created_before.as_deref())?,
+        };
+        
+        let result = self.search.aggregate(&request).await.map_err(|e| {
+            tracing::error!("Aggregate failed: {}", e);
+            internal_error(format!("Aggregate failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Write memory metadata to a CSV file
+    #[tool(description = "Export memory metadata (id, instance_id, category, tags, importance, created_at, access_count) to a CSV file, excluding content and embeddings")]
+    pub async fn rag_export_csv(
+        &self,
+        params: Parameters<RagExportCsvParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_export_csv")?;
+        let params = params.0;
+        
+        let memories = self.search
+            .list_memories(
+                params.category_filter.as_deref(),
+                params.tags_filter.as_deref(),
+                params.instance_filter.as_deref(),
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("CSV export failed: {}", e);
+                internal_error(format!("CSV export failed: {}", e), e.code())
+            })?;
+        
+        tokio::fs::write(&params.path, export::csv::metadata_csv(&memories))
+            .await
+            .map_err(|e| internal_error(format!("Failed to write {}: {}", params.path, e), INTERNAL_ERROR))?;
+        
+        let result = ExportCsvResult {
+            path: params.path,
+            rows: memories.len(),
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Render a chain as a Markdown document
+    #[tool(description = "Export a chain (memories stored with the same chain_id) in order as one Markdown document: frontmatter for the chain, a heading per memory and its metadata as a footnote. Missing links get a placeholder. Writes to path when given, otherwise returns the document.")]
+    pub async fn rag_export_chain(
+        &self,
+        params: Parameters<RagExportChainParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_export_chain")?;
+        let params = params.0;
+        if params.chain_id.trim().is_empty() {
+            return Err(invalid_input("chain_id must not be empty"));
+        }
+        
+        let entries = self.load_chain(&params.chain_id).await.map_err(|e| {
+            tracing::error!("Chain export failed: {}", e);
+            internal_error(format!("Chain export failed: {}", e), e.code())
+        })?;
+        if entries.is_empty() {
+            return Err(invalid_input(format!("Chain {} not found", params.chain_id)));
+        }
+        
+        let markdown = export::markdown::chain_markdown(&params.chain_id, &entries, Utc::now());
+        let missing = entries.iter().filter(|(_, memory)| memory.is_none()).count();
+        let mut result = ExportChainResult {
+            chain_id: params.chain_id,
+            memories: entries.len() - missing,
+            missing,
+            path: None,
+            markdown: None,
+        };
+        match params.path {
+            Some(path) => {
+                tokio::fs::write(&path, markdown)
+                    .await
+                    .map_err(|e| internal_error(format!("Failed to write {}: {}", path, e), INTERNAL_ERROR))?;
+                result.path = Some(path);
+            }
+            None => result.markdown = Some(markdown),
+        }
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Describe every tool's parameter and response schema
+    #[tool(description = "Return the JSON schema of every tool's parameters and response, for generating typed clients")]
+    pub async fn rag_schema(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_schema")?;
+        let mut responses = tools::response_schemas();
+        
+        let mut schemas = serde_json::Map::new();
+        for tool in self.tool_router.list_all() {
+            let name = tool.name.to_string();
+            schemas.insert(name.clone(), serde_json::json!({
+                "description": tool.description,
+                "params": tool.input_schema.as_ref(),
+                "response": responses.remove(&name),
+            }));
+        }
+        
+        let content = Content::json(serde_json::Value::Object(schemas))
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Report Redis cache statistics for this instance
+    #[tool(description = "Report Redis cache statistics for this instance: thought count, Redis memory usage and the compression ratio of stored thoughts")]
+    pub async fn rag_stats(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_stats")?;
+        let stats = self.cache.get_stats().await.map_err(|e| {
+            tracing::error!("Stats failed: {}", e);
+            internal_error(format!("Stats failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(stats)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Report key counts and memory usage for every instance sharing this Redis
+    #[tool(description = "Report Redis key counts and memory usage per instance for every instance that has stored memories in this Redis (tracked in um:instances)")]
+    pub async fn rag_stats_all(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_stats_all")?;
+        let instances = self.cache.instance_stats().await.map_err(|e| {
+            tracing::error!("Instance stats failed: {}", e);
+            internal_error(format!("Instance stats failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(InstanceStatsResult { instances })
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// List the instances registered in this Redis
+    #[tool(description = "List every instance that has stored memories in this Redis, with the time of its last write")]
+    pub async fn rag_instances(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_instances")?;
+        let instances = self.cache.instances().await.map_err(|e| {
+            tracing::error!("Listing instances failed: {}", e);
+            internal_error(format!("Listing instances failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(InstancesResult { instances })
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Diagnostics for the Qdrant collection
+    #[tool(description = "Report the Qdrant collection's status, vector size and distance per vector, point and indexed-vector counts, segment count, payload indexes and whether each filtered payload field is indexed")]
+    pub async fn rag_collection_info(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_collection_info")?;
+        let info = self.search.collection_info().await.map_err(|e| {
+            tracing::error!("Collection info failed: {}", e);
+            internal_error(format!("Collection info failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(info)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Back up the Qdrant collection
+    #[tool(description = "Create a Qdrant snapshot of the collection (the alias target, when aliased) on the Qdrant node and return its name, size, checksum and download path on Qdrant's HTTP API")]
+    pub async fn rag_snapshot(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_snapshot")?;
+        let result = self.search.create_snapshot().await.map_err(|e| {
+            tracing::error!("Snapshot failed: {}", e);
+            internal_error(format!("Snapshot failed: {}", e), e.code())
+        })?;
+        tracing::info!("Created snapshot {} of {}", result.snapshot.name, result.collection);
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Existing Qdrant snapshots of the collection
+    #[tool(description = "List the Qdrant snapshots of the collection stored on the Qdrant node, newest first, with name, creation time, size, checksum and download path")]
+    pub async fn rag_list_snapshots(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_list_snapshots")?;
+        let result = self.search.list_snapshots().await.map_err(|e| {
+            tracing::error!("Listing snapshots failed: {}", e);
+            internal_error(format!("Listing snapshots failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Pre-flight compatibility check of the Qdrant collection
+    #[tool(description = "Check that the Qdrant collection fits the running version and configuration: it exists, its content vector size matches the embedding model's dimension, the payload fields search filters on are indexed, and a sample of stored payloads deserializes as memories. Returns pass, warn, fail or skipped per check with a remediation hint for each problem; passed is false when any check failed")]
+    pub async fn rag_validate(&self) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_validate")?;
+        let checks = self.validation_checks().await;
+        let result = ValidateResult {
+            collection: self.config.qdrant.collection_name.clone(),
+            passed: checks.iter().all(|check| check.status != CheckStatus::Fail),
+            checks,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Count tokens with an OpenAI model's tokenizer
+    #[tool(description = "Count the tokens in text with the tiktoken encoding of an OpenAI model (default: EMBEDDING_MODEL): cl100k_base for the embedding models, GPT-4 and GPT-3.5, o200k_base for GPT-4o, GPT-4.1, GPT-5 and o-series models. Special tokens are counted as plain text. When the encoding can't be loaded, returns the conservative estimate MAX_CONTENT_TOKENS falls back to, with exact false")]
+    pub async fn rag_count_tokens(
+        &self,
+        params: Parameters<RagCountTokensParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_count_tokens")?;
+        let params = params.0;
+        let model = params.model
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| self.config.openai.model.clone());
+        let encoding = Encoding::for_model(&model);
+        
+        let result = match self.tokenizers.get(encoding).await {
+            Ok(bpe) => {
+                // Long texts take a while to merge; keep them off the async workers
+                let tokens = tokio::task::spawn_blocking(move || bpe.count(&params.text))
+                    .await
+                    .map_err(|e| internal_error(format!("Token count failed: {}", e), INTERNAL_ERROR))?;
+                CountTokensResult {
+                    tokens,
+                    model,
+                    encoding: encoding.as_str().to_string(),
+                    exact: true,
+                    warning: None,
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Falling back to estimated token count: {}", e);
+                CountTokensResult {
+                    tokens: text::estimate_tokens(&params.text),
+                    model,
+                    encoding: encoding.as_str().to_string(),
+                    exact: false,
+                    warning: Some(format!("{}; tokens is an estimate", e)),
+                }
+            }
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Wipe all data for this instance
+    #[tool(description = "Delete all Redis keys and Qdrant points belonging to this instance. Requires confirm to equal the instance id.")]
+    pub async fn rag_clear(
+        &self,
+        params: Parameters<RagClearParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_clear")?;
+        if params.0.confirm != self.instance_id {
+            return Err(invalid_input(
+                format!("confirm must equal the instance id ({}) to clear it", self.instance_id),
+            ));
+        }
+        
+        tracing::warn!("Clearing all data for instance {}", self.instance_id);
+        
+        let redis_keys_removed = self.cache.clear_instance().await.map_err(|e| {
+            internal_error(format!("Failed to clear Redis keys: {}", e), e.code())
+        })?;
+        let qdrant_points_removed = self.search.delete_instance(&self.instance_id).await.map_err(|e| {
+            internal_error(
+                format!("Cleared {} Redis keys but failed to clear Qdrant: {}", redis_keys_removed, e),
+                e.code(),
+            )
+        })?;
+        
+        let result = ClearResult {
+            instance_id: self.instance_id.clone(),
+            redis_keys_removed,
+            qdrant_points_removed,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Bulk-delete this instance's memories by category and tags
+    #[tool(description = "Delete all of this instance's memories matching a category and/or tags filter: matching ids are resolved in Qdrant and removed from Redis, then the points are deleted from Qdrant in a single filtered request")]
+    pub async fn rag_delete_by_filter(
+        &self,
+        params: Parameters<RagDeleteByFilterParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_delete_by_filter")?;
+        let params = params.0;
+        if params.category_filter.is_none() && params.tags_filter.as_ref().is_none_or(|tags| tags.is_empty()) {
+            return Err(invalid_input("category_filter or tags_filter is required; use rag_clear to delete everything"));
+        }
+        validate_filters(
+            params.category_filter.as_deref(),
+            params.category_match_mode,
+            params.tags_filter.as_deref(),
+            params.tag_min_match,
+        )?;
+        
+        let filter = MemoryFilter {
+            category_filter: params.category_filter,
+            category_match_mode: params.category_match_mode,
+            tags_filter: params.tags_filter.filter(|tags| !tags.is_empty()),
+            tag_min_match: params.tag_min_match,
+            instance_filter: Some(vec![self.instance_id.clone()]),
+        };
+        
+        // Resolve ids before deleting the points, since Redis is keyed by id
+        let ids = self.search.matching_ids(&filter).await.map_err(|e| {
+            internal_error(format!("Failed to find matching memories: {}", e), e.code())
+        })?;
+        for id in &ids {
+            self.cache.invalidate(id).await.map_err(|e| {
+                internal_error(format!("Failed to remove memory {} from Redis: {}", id, e), e.code())
+            })?;
+            if let Err(e) = self.cache.drop_relations(id).await {
+                tracing::warn!("Failed to remove links of memory {}: {}", id, e);
+            }
+        }
+        let qdrant_points_removed = self.search.delete_by_filter(&filter).await.map_err(|e| {
+            internal_error(
+                format!("Removed {} memories from Redis but failed to delete from Qdrant: {}", ids.len(), e),
+                e.code(),
+            )
+        })?;
+        
+        let result = DeleteByFilterResult {
+            instance_id: self.instance_id.clone(),
+            memories_matched: ids.len() as u64,
+            qdrant_points_removed,
+        };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Re-embed every memory into a new collection for another embedding model
+    #[tool(description = "Re-embed every memory in the collection with another embedding model into target_collection, created with that model's dimension. Memories already in the target are skipped, so an interrupted migration resumes when rerun with the same arguments. When nothing failed and alias is given, the alias is pointed at the target; passing QDRANT_COLLECTION's alias swaps the live collection. Restart the server with EMBEDDING_MODEL set to the new model to switch over")]
+    pub async fn rag_migrate_model(
+        &self,
+        params: Parameters<RagMigrateModelParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_migrate_model")?;
+        let params = params.0;
+        let model = params.model.trim().to_string();
+        if model.is_empty() {
+            return Err(invalid_input("model must not be empty"));
+        }
+        let source_collection = self.search.physical_collection().await.map_err(search_error)?;
+        let configured = &self.config.qdrant.collection_name;
+        if params.target_collection.trim().is_empty()
+            || params.target_collection == source_collection
+            || params.target_collection == *configured
+        {
+            return Err(invalid_input(format!(
+                "target_collection must name a collection other than {} and {}", configured, source_collection
+            )));
+        }
+        
+        let concurrency = params.concurrency.unwrap_or(self.config.migration_concurrency).max(1);
+        if params.dry_run {
+            let estimate = self.estimate_migration(&model, &source_collection).await?;
+            let result = MigrateModelResult {
+                model,
+                source_collection,
+                target_collection: params.target_collection,
+                dimension: None,
+                estimate: Some(estimate),
+                migrated: 0,
+                already_migrated: 0,
+                failed: 0,
+                errors: Vec::new(),
+                alias: None,
+                concurrency,
+                elapsed_ms: 0,
+                memories_per_sec: 0.0,
+            };
+            let content = Content::json(result)
+                .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+            return Ok(CallToolResult::success(vec![content]));
+        }
+        
+        let embeddings = EmbeddingGenerator::new(std::time::Duration::from_secs(self.config.openai.timeout_secs))
+            .map_err(|e| internal_error(format!("Failed to create embedding generator: {}", e), e.code()))?
+            .with_model(&model)
+            .with_batching(self.config.openai.batch_size, self.config.openai.batch_concurrency);
+        let dimension = embeddings.probe_dimension().await.map_err(|e| {
+            internal_error(format!("Failed to embed with {}: {}", model, e), e.code())
+        })?;
+        let embeddings = Arc::new(embeddings);
+        
+        let target = QdrantSearch::new(
+            self.qdrant_client.clone(),
+            params.target_collection.clone(),
+            embeddings.clone(),
+            dimension,
+            &self.config.qdrant.hnsw,
+            true,
+        )
+        .await
+        .map_err(|e| internal_error(format!("Failed to prepare {}: {}", params.target_collection, e), e.code()))?
+        .with_minimal_payload(!self.config.storage_mode.full_qdrant_payload())
+        .with_upsert_batch_size(self.config.qdrant.upsert_batch_size)
+        .with_payload_fields(self.config.qdrant.payload_fields.clone());
+        if self.config.qdrant.text_index && self.config.storage_mode.full_qdrant_payload() {
+            if let Err(e) = target.ensure_text_index().await {
+                tracing::warn!("Failed to create the full-text index on {}: {}", params.target_collection, e);
+            }
+        }
+        
+        tracing::info!(
+            "Migrating {} to {} with {} ({} dimensions)",
+            source_collection, params.target_collection, model, dimension
+        );
+        
+        // Pages are independent, so they finish in any order; embedding requests from
+        // all of them share the generator's EMBEDDING_BATCH_CONCURRENCY permits
+        let started = Instant::now();
+        let mut migrated = 0;
+        let mut already_migrated = 0;
+        let mut errors = Vec::new();
+        let mut pages = std::pin::pin!(self.search
+            .scroll_memories(None, None, None, false)
+            .map_ok(|page| self.migrate_page(page, &embeddings, &target).map(Ok))
+            .try_buffer_unordered(concurrency));
+        while let Some(page) = pages.try_next().await.map_err(|e| {
+            internal_error(format!("Scrolling {} failed after {} memories: {}", source_collection, migrated, e), e.code())
+        })? {
+            migrated += page.migrated;
+            already_migrated += page.already_migrated;
+            errors.extend(page.errors);
+        }
+        let elapsed = started.elapsed();
+        let memories_per_sec = if elapsed.as_secs_f64() > 0.0 {
+            migrated as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+        
+        let failed = errors.len();
+        let alias = match params.alias {
+            Some(alias) if failed == 0 => {
+                target.point_alias(&alias).await.map_err(|e| {
+                    internal_error(
+                        format!("Migrated {} memories but failed to point alias {}: {}", migrated, alias, e),
+                        e.code(),
+                    )
+                })?;
+                Some(alias)
+            }
+            _ => None,
+        };
+        
+        tracing::info!(
+            "Migration to {}: {} migrated, {} already there, {} failed in {:.1}s ({:.1} memories/s)",
+            params.target_collection, migrated, already_migrated, failed, elapsed.as_secs_f64(), memories_per_sec
+        );
+        
+        errors.truncate(MIGRATION_ERRORS_REPORTED);
+        let result = MigrateModelResult {
+            model,
+            source_collection,
+            target_collection: params.target_collection,
+            dimension: Some(dimension),
+            estimate: None,
+            migrated,
+            already_migrated,
+            failed,
+            errors,
+            alias,
+            concurrency,
+            elapsed_ms: elapsed.as_millis() as u64,
+            memories_per_sec,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Fold several memories into one
+    #[tool(description = "Merge memories into a primary one: appends their content to the primary's, unions tags, keeps the highest importance, re-embeds the primary and deletes the merged memories from Redis and Qdrant")]
+    pub async fn rag_merge(
+        &self,
+        params: Parameters<RagMergeParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_merge")?;
+        let params = params.0;
+        let parse = |id: &str| {
+            Uuid::parse_str(id)
+                .map(|u| u.to_string())
+                .map_err(|e| invalid_input(format!("Invalid id {}: {}", id, e)))
+        };
+        let primary_id = parse(&params.primary_id)?;
+        let mut merge_ids = Vec::new();
+        for id in &params.ids_to_merge {
+            let id = parse(id)?;
+            if id != primary_id && !merge_ids.contains(&id) {
+                merge_ids.push(id);
+            }
+        }
+        if merge_ids.is_empty() {
+            return Err(invalid_input("ids_to_merge must name at least one memory other than primary_id"));
+        }
+        
+        let (mut primary, _) = self.find_memory(&primary_id).await?;
+        let original = primary.clone();
+        let mut merged = Vec::with_capacity(merge_ids.len());
+        for id in &merge_ids {
+            merged.push(self.find_memory(id).await?.0);
+        }
+        
+        for memory in &merged {
+            primary.content.push_str("\n\n");
+            primary.content.push_str(&memory.content);
+            for tag in &memory.metadata.tags {
+                if !primary.metadata.tags.contains(tag) {
+                    primary.metadata.tags.push(tag.clone());
+                }
+            }
+            primary.metadata.importance = primary.metadata.importance.max(memory.metadata.importance);
+        }
+        let (tags, tags_truncated) = self.limit_tags(std::mem::take(&mut primary.metadata.tags)).map_err(invalid_input)?;
+        primary.metadata.tags = tags;
+        let tokens = self.count_tokens(None, &primary.content).await;
+        if tokens > self.config.openai.max_content_tokens {
+            return Err(invalid_input(
+                format!(
+                    "merged content is {} tokens, over MAX_CONTENT_TOKENS ({})",
+                    tokens, self.config.openai.max_content_tokens
+                ),
+            ));
+        }
+        
+        primary.embedding = Some(self.embed(&self.embedding_text(&primary.content)).await.map_err(|e| {
+            internal_error(format!("Failed to embed merged content: {}", e), e.code())
+        })?);
+        primary.metadata.content_hash = Some(text::content_hash(&primary.content));
+        primary.updated_at = Utc::now();
+        
+        // Overwrite the primary in place: until both layers hold the merged copy,
+        // neither it nor the sources are removed from anywhere
+        let in_redis = self.config.storage_mode.stores_in_redis();
+        if in_redis {
+            self.cache.set(&primary_id, &primary, None).await.map_err(|e| {
+                internal_error(format!("Failed to write merged memory to Redis: {}", e), e.code())
+            })?;
+        }
+        self.search.index(&primary).await.map_err(search_error)?;
+        
+        for id in &merge_ids {
+            self.remove_memory(id).await;
+        }
+        if in_redis {
+            if let Err(e) = self.cache.drop_stale_indexes(&primary_id, &original, &primary).await {
+                tracing::warn!("Failed to clean up indexes of merged memory {}: {}", primary_id, e);
+            }
+        }
+        
+        primary.embedding = None;
+        let result = MergeResult {
+            memory: primary,
+            merged_ids: merged.iter().map(|m| m.id).collect(),
+            tags_truncated,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Add tags to a memory in place
+    #[tool(description = "Add tags to a memory without re-storing it: updates the Redis copy and tag indexes and the Qdrant payload; content and embedding are untouched")]
+    pub async fn rag_add_tags(
+        &self,
+        params: Parameters<RagTagsParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_add_tags")?;
+        let result = self.edit_tags(params.0, true).await?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Remove tags from a memory in place
+    #[tool(description = "Remove tags from a memory without re-storing it: updates the Redis copy and tag indexes and the Qdrant payload; content and embedding are untouched")]
+    pub async fn rag_remove_tags(
+        &self,
+        params: Parameters<RagTagsParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_remove_tags")?;
+        let result = self.edit_tags(params.0, false).await?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Tags that appear together with a given tag
+    #[tool(description = "For a tag, list the tags most often found on the same memories in this instance, with the shared count and a Jaccard co-occurrence score (0-1), computed from the Redis tag index sets")]
+    pub async fn rag_tag_cooccurrence(
+        &self,
+        params: Parameters<RagTagCooccurrenceParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_tag_cooccurrence")?;
+        let tag = params.0.tag.trim();
+        if tag.is_empty() {
+            return Err(invalid_input("tag must not be empty"));
+        }
+        if params.0.limit == 0 {
+            return Err(invalid_input("limit must be at least 1"));
+        }
+        
+        let result = self.cache.tag_cooccurrence(tag, params.0.limit).await.map_err(|e| {
+            internal_error(format!("Tag co-occurrence failed: {}", e), e.code())
+        })?;
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Existing tags that fit new content
+    #[tool(description = "Suggest tags for content from this instance's existing tag vocabulary: finds the memories most similar to the content and ranks the tags they carry by the share of similarity behind each (0-1). Only tags in the Redis tag index are suggested; no new tags are invented")]
+    pub async fn rag_suggest_tags(
+        &self,
+        params: Parameters<RagSuggestTagsParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_suggest_tags")?;
+        let params = params.0;
+        if params.content.trim().is_empty() {
+            return Err(invalid_input("content must not be empty"));
+        }
+        if params.limit == 0 {
+            return Err(invalid_input("limit must be at least 1"));
+        }
+        if params.candidates == 0 || params.candidates > SUGGEST_TAGS_MAX_CANDIDATES {
+            return Err(invalid_input(format!("candidates must be between 1 and {}", SUGGEST_TAGS_MAX_CANDIDATES)));
+        }
+        
+        let embedding = self.embed(&self.embedding_text(&params.content)).await.map_err(search_error)?;
+        let request = SearchRequest {
+            query: String::new(),
+            limit: Some(params.candidates),
+            threshold: Some(params.threshold.unwrap_or(self.config.similarity_threshold)),
+            category_filter: None,
+            category_match_mode: CategoryMatchMode::Exact,
+            tags_filter: None,
+            tag_min_match: None,
+            instance_filter: Some(vec![self.instance_id.clone()]),
+            chain_filter: None,
+            extra_filters: None,
+            mode: SearchMode::SemanticOnly,
+            vector: VectorName::Content,
+            bypass_cache: false,
+            cache_ttl: None,
+            hnsw_ef: None,
+            all_instances: false,
+            exclude_ids: Vec::new(),
+            text_match: None,
+            model: None,
+        };
+        let found = self.search.search_with_embedding(&request, embedding).await.map_err(search_error)?;
+        let memories = self.hydrate(found.memories).await.map_err(search_error)?;
+        
+        // Each memory votes for its tags with its similarity to the content
+        let total: f64 = memories.iter().map(|m| m.relevance_score as f64).sum();
+        let mut weights: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+        for memory in &memories {
+            for tag in normalize_tags(&memory.metadata.tags) {
+                let (weight, matches) = weights.entry(tag).or_default();
+                *weight += memory.relevance_score as f64;
+                *matches += 1;
+            }
+        }
+        
+        // Qdrant payloads can carry tags whose index sets have since emptied
+        let tags: Vec<String> = weights.keys().cloned().collect();
+        let sizes = self.cache.tag_index_sizes(&tags).await.map_err(|e| {
+            internal_error(format!("Tag index lookup failed: {}", e), e.code())
+        })?;
+        let mut suggestions: Vec<TagSuggestion> = weights
+            .into_iter()
+            .zip(sizes)
+            .filter(|(_, tagged)| *tagged > 0)
+            .map(|((tag, (weight, matches)), tagged)| TagSuggestion {
+                tag,
+                score: if total > 0.0 { weight / total } else { 0.0 },
+                matches,
+                tagged,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.score.total_cmp(&a.score).then_with(|| b.tagged.cmp(&a.tagged)).then_with(|| a.tag.cmp(&b.tag))
+        });
+        suggestions.truncate(params.limit);
+        
+        let result = SuggestTagsResult {
+            memories_considered: memories.len(),
+            suggestions,
+        };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Re-assign a memory to another instance
+    #[tool(description = "Move a memory to another instance: re-keys its Redis entries (thought, metadata, tag and chain indexes) under the target prefix and updates its instance_id in Qdrant")]
+    pub async fn rag_move(
+        &self,
+        params: Parameters<RagMoveParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_move")?;
+        let params = params.0;
+        let id = Uuid::parse_str(&params.id)
+            .map_err(|e| invalid_input(format!("Invalid id: {}", e)))?
+            .to_string();
+        let target_instance = params.target_instance.trim().to_string();
+        validate_instance_id(&self.config.redis.key_scheme, &target_instance)?;
+        
+        let (mut memory, in_cache) = self.find_memory(&id).await?;
+        
+        let from_instance = std::mem::replace(&mut memory.instance_id, target_instance.clone());
+        if from_instance == target_instance {
+            return Err(invalid_input(
+                format!("Memory {} already belongs to {}", id, target_instance),
+            ));
+        }
+        memory.updated_at = Utc::now();
+        
+        let target_cache = self.cache.for_instance(&target_instance);
+        let cached = in_cache && match self.cache.move_thought(&id, &memory, &target_cache).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to move memory {} in Redis: {}", id, e);
+                false
+            }
+        };
+        
+        // Only ownership changes, so rewrite those payload fields without re-embedding;
+        // points missing from Qdrant (stored while it was down) are indexed in full
+        let ownership = serde_json::json!({
+            "instance_id": memory.instance_id,
+            "updated_at": memory.updated_at,
+        });
+        let indexed = match self.search.update_payload(&id, ownership).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!("Payload update for memory {} failed, re-indexing: {}", id, e);
+                match self.search.index(&memory).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!("Failed to re-index memory {}: {}", id, e);
+                        false
+                    }
+                }
+            }
+        };
+        
+        if !cached && !indexed {
+            return Err(internal_error(
+                format!("Failed to move memory {} in both Redis and Qdrant", id),
+                INTERNAL_ERROR,
+            ));
+        }
+        
+        // Links are per instance; the moved memory's other ends stay behind
+        if let Err(e) = self.cache.drop_relations(&id).await {
+            tracing::warn!("Failed to remove links of moved memory {}: {}", id, e);
+        }
+        
+        let result = MoveResult {
+            memory_id: memory.id,
+            from_instance,
+            to_instance: target_instance,
+            cached,
+            indexed,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Refresh one memory's content vector
+    #[tool(description = "Re-embed one memory's content and write the new vector to Qdrant: updates the point in place, or indexes the memory in full when it has no point yet (stored while embeddings failed, or under MIN_EMBED_CHARS). Content is read from Redis, falling back to Qdrant")]
+    pub async fn rag_reembed(
+        &self,
+        params: Parameters<RagReembedParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_reembed")?;
+        let id = Uuid::parse_str(&params.0.id)
+            .map_err(|e| invalid_input(format!("Invalid id: {}", e)))?
+            .to_string();
+        let (mut memory, _) = self.find_memory(&id).await?;
+        
+        // Embedded as the deferred-embedding queue would, under the global preprocessing settings
+        let prepared = self.embedding_text(&memory.content);
+        let input = text::truncate_to_tokens(&prepared, self.config.openai.max_content_tokens);
+        let embedding_truncated = input.len() < prepared.len();
+        let embedding = self.embed(input).await.map_err(|e| {
+            internal_error(format!("Failed to embed memory {}: {}", id, e), e.code())
+        })?;
+        
+        let exists = self.search.existing_ids(std::slice::from_ref(&id)).await.map_err(search_error)?.contains(&id);
+        if exists {
+            self.search.update_embedding(&id, embedding).await.map_err(search_error)?;
+        } else {
+            memory.embedding = Some(embedding);
+            self.search.index(&memory).await.map_err(search_error)?;
+        }
+        if let Err(e) = self.cache.dequeue_embedding(&id).await {
+            tracing::warn!("Failed to drop re-embedded memory {} from the embedding queue: {}", id, e);
+        }
+        
+        let result = ReembedResult {
+            memory_id: memory.id,
+            indexed: true,
+            created: !exists,
+            embedding_truncated,
+        };
+        
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Link two memories under a relation label
+    #[tool(description = "Link two memories of this instance with a typed relation (e.g. supports, contradicts, see_also). Links are bidirectional: both memories list the other under the label. Independent of parent_id and chains")]
+    pub async fn rag_link(
+        &self,
+        params: Parameters<RagLinkParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_link")?;
+        let params = params.0;
+        let source_id = parse_id("source_id", &params.source_id)?;
+        let target_id = parse_id("target_id", &params.target_id)?;
+        if source_id == target_id {
+            return Err(invalid_input("A memory can't be linked to itself"));
+        }
+        let relation = validate_relation(&params.relation)?;
+        
+        let (source, target) = (source_id.to_string(), target_id.to_string());
+        self.find_memory(&source).await?;
+        self.find_memory(&target).await?;
+        let created = self.cache.add_relation(&source, &target, &relation).await.map_err(|e| {
+            internal_error(format!("Failed to record link in Redis: {}", e), e.code())
+        })?;
+        
+        let result = LinkResult {
+            source_id,
+            target_id,
+            relation,
+            created,
+        };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// Remove links between two memories
+    #[tool(description = "Remove the links between two memories, from both sides: only the given relation, or every relation between them when none is given")]
+    pub async fn rag_unlink(
+        &self,
+        params: Parameters<RagUnlinkParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_unlink")?;
+        let params = params.0;
+        let source_id = parse_id("source_id", &params.source_id)?;
+        let target_id = parse_id("target_id", &params.target_id)?;
+        let relation = params.relation.as_deref().map(validate_relation).transpose()?;
+        
+        let removed = self.cache
+            .remove_relations(&source_id.to_string(), &target_id.to_string(), relation.as_deref())
+            .await
+            .map_err(|e| internal_error(format!("Failed to remove links in Redis: {}", e), e.code()))?;
+        
+        let result = UnlinkResult {
+            source_id,
+            target_id,
+            removed,
+        };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+    
+    /// List a memory's links
+    #[tool(description = "List the memories linked to a memory with rag_link, with each link's relation label; optionally only one relation")]
+    pub async fn rag_relations(
+        &self,
+        params: Parameters<RagRelationsParams>,
+    ) -> std::result::Result<CallToolResult, ErrorData> {
+        self.check_rate_limit("rag_relations")?;
+        let params = params.0;
+        let id = parse_id("id", &params.id)?;
+        let wanted = params.relation.as_deref().map(validate_relation).transpose()?;
+        
+        let relations = self.cache.relations(&id.to_string()).await.map_err(|e| {
+            internal_error(format!("Failed to read links from Redis: {}", e), e.code())
+        })?;
+        let relations = relations
+            .into_iter()
+            .filter(|(_, relation)| wanted.as_ref().is_none_or(|w| w == relation))
+            .filter_map(|(other, relation)| Some(Relation { id: Uuid::parse_str(&other).ok()?, relation }))
+            .collect();
+        
+        let result = RelationsResult {
+            memory_id: id,
+            relations,
+        };
+        let content = Content::json(result)
+            .map_err(|e| internal_error(format!("Failed to create JSON content: {}", e), INTERNAL_ERROR))?;
+        Ok(CallToolResult::success(vec![content]))
+    }
+}
+
+/// Parse a memory id tool parameter
+fn parse_id(name: &str, id: &str) -> std::result::Result<Uuid, ErrorData> {
+    Uuid::parse_str(id).map_err(|e| invalid_input(format!("Invalid {}: {}", name, e)))
+}
+
+/// Drop results from the end of a page until its JSON fits in `max_bytes`, pointing
+/// `next_offset` at the first dropped one. Keeps the page as is when it already fits.
+fn fit_response(result: &mut SearchResult, offset: usize, max_bytes: usize) {
+    fn json_len<T: serde::Serialize>(value: &T) -> usize {
+        serde_json::to_vec(value).map(|json| json.len()).unwrap_or(0)
+    }
+    if json_len(result) <= max_bytes {
+        return;
+    }
+    
+    // Measure the page without memories but with the truncation fields set, then add
+    // memories while they fit; each one after the first also costs a comma
+    let mut memories = std::mem::take(&mut result.memories);
+    result.response_truncated = true;
+    result.next_offset = Some(offset + memories.len());
+    let mut used = json_len(result);
+    let mut kept = 0;
+    for (i, memory) in memories.iter().enumerate() {
+        let len = json_len(memory) + usize::from(i > 0);
+        if used + len > max_bytes {
+            break;
+        }
+        used += len;
+        kept += 1;
+    }
+    
+    memories.truncate(kept);
+    result.memories = memories;
+    result.total_results = kept;
+    result.next_offset = Some(offset + kept);
+    if kept == 0 {
+        result.warnings.push(format!(
+            "The first result alone exceeds MAX_RESPONSE_BYTES ({} bytes); retry with max_content_chars",
+            max_bytes
+        ));
+    }
+}
+
+/// Trimmed tags without empty ones or repeats, in first-seen order
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// Relation labels are trimmed and must be non-empty, without control characters
+fn validate_relation(relation: &str) -> std::result::Result<String, ErrorData> {
+    let relation = relation.trim();
+    if relation.is_empty() {
+        return Err(invalid_input("relation must not be empty"));
+    }
+    if relation.chars().any(char::is_control) {
+        return Err(invalid_input("relation must not contain control characters"));
+    }
+    Ok(relation.to_string())
+}
+
+/// Parse an optional RFC 3339 tool parameter
+fn parse_timestamp(
+    name: &str,
+    value: Option<&str>,
+) -> std::result::Result<Option<chrono::DateTime<chrono::Utc>>, ErrorData> {
+    value
+        .map(|v| {
+            chrono::DateTime::parse_from_rfc3339(v)
+                .map(|d| d.with_timezone(&chrono::Utc))
+                .map_err(|e| invalid_input(format!("Invalid {}: {}", name, e)))
+        })
+        .transpose()
+}
+
+/// Check the category and tag filters shared by the search tools
+fn validate_filters(
+    category_filter: Option<&str>,
+    category_match_mode: CategoryMatchMode,
+    tags_filter: Option<&[String]>,
+    tag_min_match: Option<usize>,
+) -> std::result::Result<(), ErrorData> {
+    if let Some(min_match) = tag_min_match {
+        let tag_count = tags_filter.map_or(0, <[String]>::len);
+        if min_match == 0 || min_match > tag_count {
+            return Err(invalid_input(
+                format!("tag_min_match must be between 1 and the number of tags_filter entries ({})", tag_count),
+            ));
+        }
+    }
+    if let Some(category) = category_filter {
+        if category_match_mode.normalize(category).is_empty() {
+            return Err(invalid_input("category_filter must name a category"));
+        }
+    }
+    Ok(())
+}
+
+/// Extra metadata keys become Qdrant payload paths (`metadata.extra.<key>`), so
+/// they can't contain path syntax
+fn validate_extra_key(key: &str) -> std::result::Result<(), ErrorData> {
+    if key.trim().is_empty() || key.contains(['.', '[', ']']) {
+        return Err(invalid_input(format!("extra field name {:?} must be non-empty and contain no '.', '[' or ']'", key)));
+    }
+    Ok(())
+}
+
+/// `extra_filters` values must be ones Qdrant can match exactly: strings, integers or booleans
+fn validate_extra_filters(filters: Option<&BTreeMap<String, serde_json::Value>>) -> std::result::Result<(), ErrorData> {
+    for (key, value) in filters.into_iter().flatten() {
+        validate_extra_key(key)?;
+        let matchable = value.is_string() || value.is_i64() || value.is_u64() || value.is_boolean();
+        if !matchable {
+            return Err(invalid_input(format!("extra_filters.{} must be a string, integer or boolean", key)));
+        }
+    }
+    Ok(())
+}
+
+#[tool_handler]
+impl ServerHandler for UnifiedRagService {
+    fn get_info(&self) -> rmcp::model::ServerInfo {
+        rmcp::model::ServerInfo {
+            protocol_version: rmcp::model::ProtocolVersion::V_2024_11_05,
+            server_info: rmcp::model::Implementation {
+                name: "unified-rag".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            capabilities: rmcp::model::ServerCapabilities {
+                tools: Some(Default::default()),
+                ..Default::default()
+            },
+            instructions: Some("UnifiedRAG MCP Server - Hybrid search with Redis cache and Qdrant semantic search".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn instance_ids_that_shadow_other_instances_are_rejected() {
+        let keys = KeyScheme::default();
+        for target in ["", "team:CC", "a*", "x[ab]"] {
+            let error = validate_instance_id(&keys, target).unwrap_err();
+            assert_eq!(error.data.unwrap()["code"], INVALID_INPUT, "{:?}", target);
+        }
+        assert!(validate_instance_id(&keys, "DT").is_ok());
+    }
+    
+    #[test]
+    fn summary_with_another_model_is_rejected_on_named_vectors() {
+        let default = "text-embedding-3-small";
+        let error = validate_summary_model(Some("text-embedding-3-large"), default, true, true).unwrap_err();
+        assert_eq!(error.data.unwrap()["code"], INVALID_INPUT);
+        
+        // Same model, no summary, or no summary vector to put it in
+        assert!(validate_summary_model(Some(default), default, true, true).is_ok());
+        assert!(validate_summary_model(Some("text-embedding-3-large"), default, false, true).is_ok());
+        assert!(validate_summary_model(Some("text-embedding-3-large"), default, true, false).is_ok());
+        assert!(validate_summary_model(None, default, true, true).is_ok());
+    }
+}
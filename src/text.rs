@@ -0,0 +1,485 @@
+use std::borrow::Cow;
+use icu_normalizer::ComposingNormalizerBorrowed;
+use ring::digest::{digest, SHA256};
+
+/// Normalize text to Unicode NFC so visually identical strings hash and embed the same
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    ComposingNormalizerBorrowed::new_nfc().normalize(text)
+}
+
+/// Hex-encoded sha256, used for all derived (content/query hash) keys
+pub fn sha256_hex(data: &[u8]) -> String {
+    digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// sha256 of the NFC-normalized content, stored as `metadata.content_hash`
+pub fn content_hash(content: &str) -> String {
+    sha256_hex(normalize(content).as_bytes())
+}
+
+/// Conservative token count for cl100k-style BPE tokenizers.
+///
+/// English averages about four ASCII characters per token; other scripts often
+/// take a token or more per character, so each non-ASCII character counts as one.
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+        if c.is_ascii() { (a + 1, o) } else { (a, o + 1) }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Longest prefix of `text` whose `estimate_tokens` stays within `max_tokens`, cut
+/// back to the last whitespace so no word is split. Returns `text` when it already fits.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    let (mut ascii, mut other) = (0usize, 0usize);
+    for (i, c) in text.char_indices() {
+        if c.is_ascii() { ascii += 1 } else { other += 1 }
+        if ascii.div_ceil(4) + other > max_tokens {
+            let cut = text[..i].rfind(char::is_whitespace).filter(|&w| w > 0).unwrap_or(i);
+            return text[..cut].trim_end();
+        }
+    }
+    text
+}
+
+/// Chunk `text` so that every chunk's `estimate_tokens` stays within `max_tokens`
+pub fn chunk_by_tokens(text: &str, max_tokens: usize) -> Vec<String> {
+    let chars = text.chars().count().max(1);
+    let tokens = estimate_tokens(text).max(1);
+    let mut max_chars = (max_tokens * chars / tokens).max(1);
+    
+    loop {
+        let chunks = chunk(text, max_chars);
+        if max_chars == 1 || chunks.iter().all(|c| estimate_tokens(c) <= max_tokens) {
+            return chunks;
+        }
+        max_chars /= 2;
+    }
+}
+
+/// Split text into chunks of at most `max_chars` characters.
+///
+/// Paragraphs (blank-line separated) are packed together while they fit; a
+/// paragraph longer than `max_chars` is split at the last whitespace before the limit.
+pub fn chunk(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let needed = if current.is_empty() { 0 } else { 2 } + paragraph.chars().count();
+        if current.chars().count() + needed > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        
+        let mut rest = paragraph;
+        while rest.chars().count() > max_chars {
+            let limit = rest.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(rest.len());
+            let split = rest[..limit].rfind(char::is_whitespace).filter(|&i| i > 0).unwrap_or(limit);
+            chunks.push(rest[..split].trim_end().to_string());
+            rest = rest[split..].trim_start();
+        }
+        
+        if !rest.is_empty() {
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(rest);
+        }
+    }
+    
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Cut `text` to at most `max_chars` characters, ending with `…` when anything was removed.
+/// Returns `None` when the text already fits.
+pub fn truncate(text: &str, max_chars: usize) -> Option<String> {
+    let (end, _) = text.char_indices().nth(max_chars)?;
+    let keep = text.char_indices().nth(max_chars.saturating_sub(1)).map(|(i, _)| i).unwrap_or(end);
+    Some(format!("{}…", text[..keep].trim_end()))
+}
+
+/// Fraction of distinct query terms (case-insensitive, alphanumeric) present in `content`
+pub fn keyword_overlap(query: &str, content: &str) -> f32 {
+    let terms = |s: &str| -> std::collections::HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_lowercase())
+            .collect()
+    };
+    
+    let query_terms = terms(query);
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+    let content_terms = terms(content);
+    let matched = query_terms.iter().filter(|t| content_terms.contains(*t)).count();
+    matched as f32 / query_terms.len() as f32
+}
+
+/// Byte range of the sentence in `content` sharing the most terms with `query`.
+///
+/// Sentences end after `.`, `!` or `?` followed by whitespace, or at a line break.
+/// Ties go to the earliest sentence; `None` when no sentence contains a query term.
+pub fn best_sentence(query: &str, content: &str) -> Option<(usize, usize)> {
+    let mut best: Option<((usize, usize), f32)> = None;
+    let mut start = 0;
+    let mut chars = content.char_indices().peekable();
+    
+    while let Some((i, c)) = chars.next() {
+        let next_is_space = chars.peek().is_none_or(|(_, n)| n.is_whitespace());
+        let at_end = chars.peek().is_none();
+        let boundary = c == '\n' || (matches!(c, '.' | '!' | '?') && next_is_space);
+        if !boundary && !at_end {
+            continue;
+        }
+        
+        let end = i + c.len_utf8();
+        let raw = &content[start..end];
+        let sentence = raw.trim();
+        if !sentence.is_empty() {
+            let score = keyword_overlap(query, sentence);
+            if score > 0.0 && best.is_none_or(|(_, s)| score > s) {
+                let offset = start + (raw.len() - raw.trim_start().len());
+                best = Some(((offset, offset + sentence.len()), score));
+            }
+        }
+        start = end;
+    }
+    
+    best.map(|(span, _)| span)
+}
+
+/// Content as sent to the embedding model: HTML stripped first (Markdown often
+/// embeds it), then Markdown. Falls back to `text` when nothing readable is left.
+pub fn preprocess(text: &str, markdown: bool, html: bool) -> Cow<'_, str> {
+    let mut out = Cow::Borrowed(text);
+    if html {
+        out = Cow::Owned(strip_html(&out));
+    }
+    if markdown {
+        out = Cow::Owned(strip_markdown(&out));
+    }
+    if out.trim().is_empty() {
+        return Cow::Borrowed(text);
+    }
+    out
+}
+
+/// Markdown reduced to its readable text, for embedding.
+///
+/// Line-based: frontmatter, fences, rules, table separators and link definitions
+/// are dropped; heading, quote and list markers are removed; code keeps its text.
+/// Inline emphasis and code markers go, links and images keep their label, and
+/// wikilinks keep their alias or target.
+pub fn strip_markdown(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in without_frontmatter(text).lines() {
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            } else {
+                out.push(line.to_string());
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        if is_rule(trimmed) || is_link_definition(trimmed) {
+            continue;
+        }
+        
+        let mut rest = trimmed;
+        while let Some(quoted) = rest.strip_prefix('>') {
+            rest = quoted.trim_start();
+        }
+        rest = rest.trim_start_matches('#').trim_start();
+        rest = strip_list_marker(rest);
+        let line = strip_inline_markdown(rest);
+        out.push(line.replace('|', " ").split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    
+    collapse_blank_lines(&out.join("\n"))
+}
+
+/// `text` after a leading `---` frontmatter block; all of it when there is none
+fn without_frontmatter(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("---").filter(|r| r.starts_with(['\n', '\r'])) else {
+        return text;
+    };
+    match rest.find("\n---") {
+        Some(end) => rest[end + 4..].split_once('\n').map_or("", |(_, body)| body),
+        None => text,
+    }
+}
+
+/// `---`, `***`, `___` rules and table separator rows like `|---|:--:|`
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    if marks.len() >= 3 && ["-", "*", "_"].iter().any(|m| marks.chars().all(|c| c.to_string() == *m)) {
+        return true;
+    }
+    marks.contains('-') && marks.contains('|') && marks.chars().all(|c| matches!(c, '|' | '-' | ':'))
+}
+
+/// Reference link definitions like `[id]: https://example.com`
+fn is_link_definition(line: &str) -> bool {
+    line.starts_with('[') && !line.starts_with("[[") && line.find("]:").is_some_and(|i| !line[1..i].contains(']'))
+}
+
+/// Bullet, ordered-list and task-box markers at the start of a line
+fn strip_list_marker(line: &str) -> &str {
+    let rest = if let Some(rest) = line.strip_prefix(['-', '*', '+']).filter(|r| r.starts_with(' ')) {
+        rest.trim_start()
+    } else {
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        match line[digits..].strip_prefix(['.', ')']) {
+            Some(rest) if digits > 0 && rest.starts_with(' ') => rest.trim_start(),
+            _ => return line,
+        }
+    };
+    ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|b| rest.strip_prefix(b))
+        .unwrap_or(rest)
+}
+
+fn strip_inline_markdown(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let find = |from: usize, target: &[char]| -> Option<usize> {
+        (from..=chars.len().saturating_sub(target.len())).find(|&j| chars[j..].starts_with(target))
+    };
+    
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&b| b == '`').count();
+                let ticks = vec!['`'; run];
+                match find(i + run, &ticks) {
+                    Some(close) => {
+                        out.extend(&chars[i + run..close]);
+                        i = close + run;
+                    }
+                    None => {
+                        out.extend(&ticks);
+                        i += run;
+                    }
+                }
+            }
+            '[' if chars.get(i + 1) == Some(&'[') => match find(i + 2, &[']', ']']) {
+                Some(close) => {
+                    let inner: String = chars[i + 2..close].iter().collect();
+                    out.push_str(inner.rsplit('|').next().unwrap_or(&inner));
+                    i = close + 2;
+                }
+                None => {
+                    out.push_str("[[");
+                    i += 2;
+                }
+            },
+            '!' | '[' => {
+                let open = if c == '!' { i + 1 } else { i };
+                let link = (chars.get(open) == Some(&'['))
+                    .then(|| find(open + 1, &[']']))
+                    .flatten()
+                    .and_then(|close| {
+                        let end = match chars.get(close + 1) {
+                            Some('(') => find(close + 2, &[')'])?,
+                            Some('[') => find(close + 2, &[']'])?,
+                            _ if c == '[' => close,
+                            _ => return None,
+                        };
+                        Some((close, end))
+                    });
+                match link {
+                    Some((close, end)) => {
+                        let label: String = chars[open + 1..close].iter().collect();
+                        out.push_str(&strip_inline_markdown(&label));
+                        i = end + 1;
+                    }
+                    None => {
+                        out.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '<' => {
+                let autolink = find(i + 1, &['>']).filter(|&close| {
+                    let inner: String = chars[i + 1..close].iter().collect();
+                    !inner.contains(char::is_whitespace) && (inner.contains("://") || inner.contains('@'))
+                });
+                match autolink {
+                    Some(close) => {
+                        out.extend(&chars[i + 1..close]);
+                        i = close + 1;
+                    }
+                    None => {
+                        out.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' | '~' => {
+                let run = chars[i..].iter().take_while(|&&b| b == c).count();
+                let before = i.checked_sub(1).map(|j| chars[j]);
+                let after = chars.get(i + run).copied();
+                let word = |ch: Option<char>| ch.is_some_and(char::is_alphanumeric);
+                let space = |ch: Option<char>| ch.is_none_or(char::is_whitespace);
+                // Keep snake_case, `2 * 3` and single tildes
+                let literal = match c {
+                    '_' => word(before) && word(after),
+                    '*' => space(before) && space(after),
+                    _ => run != 2,
+                };
+                if literal {
+                    out.extend(std::iter::repeat_n(c, run));
+                }
+                i += run;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// HTML reduced to its text, for embedding.
+///
+/// Tags and comments are removed, with block-level tags becoming line breaks;
+/// `script` and `style` elements are dropped with their content, and common
+/// entities are decoded.
+pub fn strip_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&decode_entities(&rest[..start]));
+        let tag = &rest[start..];
+        
+        if let Some(comment) = tag.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let name: String = tag[1..]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let is_tag = !name.is_empty() || tag[1..].starts_with(['/', '!', '?']);
+        let Some(end) = tag.find('>').filter(|_| is_tag) else {
+            out.push('<');
+            rest = &tag[1..];
+            continue;
+        };
+        
+        rest = &tag[end + 1..];
+        if matches!(name.as_str(), "script" | "style") && !tag[1..].starts_with('/') {
+            let closing = format!("</{}", name);
+            rest = rest.to_ascii_lowercase().find(&closing)
+                .and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+                .unwrap_or("");
+        } else if BLOCK_TAGS.contains(&name.as_str()) {
+            out.push('\n');
+        } else {
+            out.push(' ');
+        }
+    }
+    out.push_str(&decode_entities(rest));
+    
+    let lines: Vec<String> = out.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+    collapse_blank_lines(&lines.join("\n"))
+}
+
+/// Tags whose start or end breaks the line when stripped
+const BLOCK_TAGS: [&str; 24] = [
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "footer", "h1", "h2",
+    "h3", "h4", "h5", "h6", "header", "hr", "li", "p", "pre", "section", "table", "tr",
+];
+
+/// Decode named entities common in prose and numeric character references;
+/// anything else is left as written
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let entity = &rest[start + 1..];
+        let decoded = entity.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let name = &entity[..end];
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                _ => {
+                    let code = name.strip_prefix('#')?;
+                    let code = match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => code.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &entity[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = entity;
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Trim, and squeeze runs of blank lines down to one
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(line.trim_end());
+    }
+    out
+}
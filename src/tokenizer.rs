@@ -0,0 +1,212 @@
+use base64::Engine;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use crate::error::{Result, UnifiedRagError};
+
+/// Where tiktoken publishes its encoding files
+const ENCODINGS_URL: &str = "https://openaipublic.blob.core.windows.net/encodings";
+
+/// cl100k_base pre-tokenizer without tiktoken's `\s+(?!\S)` branch, which the regex
+/// crate can't express; `Bpe::pieces` gives whitespace runs back their last character instead
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+/// o200k_base pre-tokenizer, with the same lookahead branch removed as `CL100K_PATTERN`
+const O200K_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+",
+);
+
+/// The tiktoken encodings OpenAI's current models use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Cl100kBase,
+    O200kBase,
+}
+
+impl Encoding {
+    /// Encoding of an OpenAI model: o200k_base for GPT-4o, GPT-4.1, GPT-5 and the
+    /// o-series, cl100k_base for everything else (embedding models, GPT-4, GPT-3.5)
+    pub fn for_model(model: &str) -> Self {
+        let model = model.trim().to_ascii_lowercase();
+        let o200k = ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "chatgpt-4o", "o1", "o3", "o4"];
+        if o200k.iter().any(|prefix| model.starts_with(prefix)) {
+            Encoding::O200kBase
+        } else {
+            Encoding::Cl100kBase
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::O200kBase => "o200k_base",
+        }
+    }
+    
+    fn pattern(&self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => CL100K_PATTERN,
+            Encoding::O200kBase => O200K_PATTERN,
+        }
+    }
+}
+
+/// A loaded tiktoken encoding: byte-pair ranks plus its pre-tokenizer
+pub struct Bpe {
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl Bpe {
+    /// Parse a `.tiktoken` file: one base64 token and its rank per line
+    pub fn parse(encoding: Encoding, data: &str) -> Result<Self> {
+        let invalid = |line: usize, reason: String| {
+            UnifiedRagError::Configuration(format!("Invalid {} file at line {}: {}", encoding.as_str(), line, reason))
+        };
+        
+        let mut ranks = HashMap::new();
+        for (i, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let (token, rank) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid(i + 1, "expected a token and a rank".to_string()))?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|e| invalid(i + 1, e.to_string()))?;
+            let rank: u32 = rank.trim().parse().map_err(|e| invalid(i + 1, format!("{}", e)))?;
+            ranks.insert(token, rank);
+        }
+        // Every single byte is a token, so no input is left unencodable
+        if let Some(byte) = (0..=u8::MAX).find(|b| !ranks.contains_key([*b].as_slice())) {
+            return Err(UnifiedRagError::Configuration(format!(
+                "{} file has no token for byte {}", encoding.as_str(), byte
+            )));
+        }
+        
+        let pattern = Regex::new(encoding.pattern())
+            .map_err(|e| UnifiedRagError::Configuration(format!("Invalid {} pattern: {}", encoding.as_str(), e)))?;
+        Ok(Self { ranks, pattern })
+    }
+    
+    /// Tokens in `text`, encoded as ordinary text: special tokens such as
+    /// `<|endoftext|>` count as the characters they're written with
+    pub fn count(&self, text: &str) -> usize {
+        self.pieces(text).map(|piece| self.piece_tokens(piece.as_bytes())).sum()
+    }
+    
+    /// Split `text` the way tiktoken's pre-tokenizer does. A whitespace run followed by
+    /// other text leaves its last character to start the next piece, as `\s+(?!\S)` would.
+    fn pieces<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            let found = self.pattern.find_at(text, start)?;
+            let mut end = found.end();
+            let piece = found.as_str();
+            let followed_by_text = text[end..].chars().next().is_some_and(|c| !c.is_whitespace());
+            if followed_by_text && piece.chars().all(char::is_whitespace) && !piece.ends_with(['\r', '\n']) {
+                if let Some((last, _)) = piece.char_indices().last().filter(|(last, _)| *last > 0) {
+                    end = found.start() + last;
+                }
+            }
+            start = end;
+            Some(&text[found.start()..end])
+        })
+    }
+    
+    /// Tokens for one pre-tokenized piece: repeatedly merge the adjacent pair with the
+    /// lowest rank, leftmost first, until no pair is a token
+    fn piece_tokens(&self, piece: &[u8]) -> usize {
+        if piece.is_empty() {
+            return 0;
+        }
+        if piece.len() == 1 || self.ranks.contains_key(piece) {
+            return 1;
+        }
+        
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        while let Some((_, i)) = bounds
+            .windows(3)
+            .enumerate()
+            .filter_map(|(i, w)| self.ranks.get(&piece[w[0]..w[2]]).map(|&rank| (rank, i)))
+            .min()
+        {
+            bounds.remove(i + 1);
+        }
+        bounds.len() - 1
+    }
+}
+
+/// Encodings loaded on first use and kept for the life of the server. Each is read
+/// from `dir` when the file is there, otherwise downloaded (and saved to `dir`, if set).
+pub struct Tokenizers {
+    client: reqwest::Client,
+    dir: Option<PathBuf>,
+    cl100k: OnceCell<Arc<Bpe>>,
+    o200k: OnceCell<Arc<Bpe>>,
+}
+
+impl Tokenizers {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            dir,
+            cl100k: OnceCell::new(),
+            o200k: OnceCell::new(),
+        }
+    }
+    
+    /// The loaded encoding; a failed load is retried on the next call
+    pub async fn get(&self, encoding: Encoding) -> Result<Arc<Bpe>> {
+        let cell = match encoding {
+            Encoding::Cl100kBase => &self.cl100k,
+            Encoding::O200kBase => &self.o200k,
+        };
+        cell.get_or_try_init(|| async { self.load(encoding).await.map(Arc::new) })
+            .await
+            .cloned()
+    }
+    
+    async fn load(&self, encoding: Encoding) -> Result<Bpe> {
+        let file_name = format!("{}.tiktoken", encoding.as_str());
+        let path = self.dir.as_ref().map(|dir| dir.join(&file_name));
+        if let Some(ref path) = path {
+            if let Ok(data) = tokio::fs::read_to_string(path).await {
+                tracing::info!("Loaded {} from {}", encoding.as_str(), path.display());
+                return Bpe::parse(encoding, &data);
+            }
+        }
+        
+        let url = format!("{}/{}", ENCODINGS_URL, file_name);
+        let data = self.client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| UnifiedRagError::Configuration(format!("Failed to download {}: {}", url, e)))?
+            .text()
+            .await
+            .map_err(|e| UnifiedRagError::Configuration(format!("Failed to download {}: {}", url, e)))?;
+        let bpe = Bpe::parse(encoding, &data)?;
+        tracing::info!("Downloaded {} from {}", encoding.as_str(), url);
+        
+        if let Some(path) = path {
+            let saved = async {
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                tokio::fs::write(&path, &data).await
+            };
+            if let Err(e) = saved.await {
+                tracing::warn!("Failed to save {} to {}: {}", encoding.as_str(), path.display(), e);
+            }
+        }
+        Ok(bpe)
+    }
+}
@@ -1,5 +1,12 @@
 use rmcp::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::models::{
+    AggregateField, AggregateResult, CategoryMatchMode, ClearResult, CollectionInfoResult, CountTokensResult, DeleteByFilterResult, ExportChainResult, ExportCsvResult, GetManyResult, ImportVaultResult, LinkResult, MergeResult, MigrateModelResult, MoveResult, ReembedResult, RelationsResult, SearchMode, SearchResult,
+    SnapshotResult, SnapshotsResult, StoreAndRelateResult, StoreResult, SuggestTagsResult, TagCooccurrenceResult, TagsResult, UnlinkResult, ValidateResult, VectorName,
+};
+use crate::cache::{CacheStats, InstanceStatsResult, InstancesResult};
+use rmcp::handler::server::tool::schema_for_type;
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RagSearchParams {
@@ -10,22 +17,162 @@ pub struct RagSearchParams {
     #[serde(default = "default_limit")]
     pub limit: usize,
     
-    /// Similarity threshold for semantic search (0.0-1.0, default: 0.7)
-    #[serde(default = "default_threshold")]
-    pub threshold: f32,
+    /// Skip this many top results, e.g. the next_offset of a response_truncated page (default: 0)
+    #[serde(default)]
+    pub offset: usize,
+    
+    /// Similarity threshold for semantic search (0.0-1.0, default: SIMILARITY_THRESHOLD, 0.7 unless configured)
+    pub threshold: Option<f32>,
     
     /// Filter by category
     pub category_filter: Option<String>,
     
+    /// How category_filter matches: exact (default) or prefix, where `project/*` matches `project` and everything below it
+    #[serde(default)]
+    pub category_match_mode: CategoryMatchMode,
+    
     /// Filter by tags
     pub tags_filter: Option<Vec<String>>,
     
-    /// Filter by instance IDs
+    /// Match memories having at least this many of tags_filter (1..=number of tags)
+    pub tag_min_match: Option<usize>,
+    
+    /// Filter by instance IDs (default: the searching instance, or all with search_all_instances)
     pub instance_filter: Option<Vec<String>>,
     
-    /// Use hybrid search (cache + semantic)
+    /// Only search memories stored on this chain_id
+    pub chain_filter: Option<String>,
+    
+    /// Only search memories whose extra metadata has each of these values (strings, integers or booleans; an array field matches if it contains the value)
+    pub extra_filters: Option<BTreeMap<String, serde_json::Value>>,
+    
+    /// IDs of memories to leave out of the results, e.g. one just stored (default: none)
+    #[serde(default)]
+    pub exclude_ids: Vec<String>,
+    
+    /// Only return memories whose content contains every word of this text (case-insensitive), using Qdrant's full-text index on content
+    pub text_match: Option<String>,
+    
+    /// OpenAI embedding model for the query instead of EMBEDDING_MODEL; its vectors must have the collection's dimension
+    pub model: Option<String>,
+    
+    /// Use hybrid search (cache + semantic); ignored when mode is given
     #[serde(default = "default_hybrid")]
     pub hybrid_mode: bool,
+    
+    /// Backends to search: hybrid (Redis cache + Qdrant), semantic_only (Qdrant) or cache_only (Redis metadata filters ranked by keyword overlap, with no embedding, OpenAI call or semantic ranking). Overrides hybrid_mode
+    pub mode: Option<SearchMode>,
+    
+    /// Hybrid fusion weight: 1.0 ranks purely by semantic score, 0.0 purely by cache keyword match (default: 0.5)
+    #[serde(default = "default_hybrid_alpha")]
+    pub hybrid_alpha: f32,
+    
+    /// Rescore the top candidates with the configured cross-encoder before returning (default: false)
+    #[serde(default)]
+    pub rerank: bool,
+    
+    /// Named vector to search: content (default) or summary
+    #[serde(default)]
+    pub vector: VectorName,
+    
+    /// Cut each returned memory's content to this many characters, marking it truncated (default: no limit)
+    pub max_content_chars: Option<usize>,
+    
+    /// Return the sentence that best matches the query for each result, with character offsets (default: false)
+    #[serde(default)]
+    pub snippets: bool,
+    
+    /// Ignore cached search results for this call and refresh them with fresh ones (default: false)
+    #[serde(default)]
+    pub bypass_cache: bool,
+    
+    /// Seconds to cache this query's results, overriding SEARCH_CACHE_TTL; 0 neither reads nor writes the cache for it
+    pub cache_ttl: Option<u64>,
+    
+    /// Qdrant HNSW ef for this query: higher values raise recall at the cost of latency (default: Qdrant's)
+    pub search_ef: Option<u64>,
+    
+    /// Search across all instances (cache and Qdrant) and drop duplicate content; cannot be combined with instance_filter (default: false)
+    #[serde(default)]
+    pub search_all_instances: bool,
+    
+    /// Search as this instance instead of the server's INSTANCE_ID: its Redis keys are scanned in hybrid mode and Qdrant is limited to its memories unless instance_filter is given
+    pub instance: Option<String>,
+    
+    /// Halve a result's score for every this-many days of age (default: no decay)
+    pub recency_half_life_days: Option<f64>,
+    
+    /// Add a per-stage latency breakdown (embedding, cache, Qdrant, hydrate, rerank, total) to the result (default: false)
+    #[serde(default)]
+    pub timings: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagSearchByEmbeddingParams {
+    /// Query vector; its length must equal the collection's embedding dimension
+    pub embedding: Vec<f32>,
+    
+    /// Maximum number of results to return (default: 20)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    
+    /// Similarity threshold for semantic search (0.0-1.0, default: SIMILARITY_THRESHOLD, 0.7 unless configured)
+    pub threshold: Option<f32>,
+    
+    /// Filter by category
+    pub category_filter: Option<String>,
+    
+    /// How category_filter matches: exact (default) or prefix, where `project/*` matches `project` and everything below it
+    #[serde(default)]
+    pub category_match_mode: CategoryMatchMode,
+    
+    /// Filter by tags
+    pub tags_filter: Option<Vec<String>>,
+    
+    /// Match memories having at least this many of tags_filter (1..=number of tags)
+    pub tag_min_match: Option<usize>,
+    
+    /// Filter by instance IDs (default: the searching instance, or all with search_all_instances)
+    pub instance_filter: Option<Vec<String>>,
+    
+    /// Only search memories stored on this chain_id
+    pub chain_filter: Option<String>,
+    
+    /// Only search memories whose extra metadata has each of these values (strings, integers or booleans; an array field matches if it contains the value)
+    pub extra_filters: Option<BTreeMap<String, serde_json::Value>>,
+    
+    /// IDs of memories to leave out of the results (default: none)
+    #[serde(default)]
+    pub exclude_ids: Vec<String>,
+    
+    /// Named vector to search: content (default) or summary
+    #[serde(default)]
+    pub vector: VectorName,
+    
+    /// Qdrant HNSW ef for this query: higher values raise recall at the cost of latency (default: Qdrant's)
+    pub search_ef: Option<u64>,
+    
+    /// Search across all instances and drop duplicate content; cannot be combined with instance_filter (default: false)
+    #[serde(default)]
+    pub search_all_instances: bool,
+    
+    /// Search as this instance instead of the server's INSTANCE_ID: Qdrant is limited to its memories unless instance_filter is given
+    pub instance: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagGetManyParams {
+    /// Memory IDs to load, at most 100; the result keeps this order, duplicates included
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagCountTokensParams {
+    /// Text to count
+    pub text: String,
+    
+    /// OpenAI model whose tokenizer to use (default: EMBEDDING_MODEL)
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -51,8 +198,305 @@ pub struct RagStoreParams {
     
     /// Thinking framework used
     pub framework: Option<String>,
+    
+    /// Precomputed embedding to index instead of calling OpenAI; must match the collection dimension
+    pub embedding: Option<Vec<f32>>,
+    
+    /// Short summary, embedded under the summary vector so rag_search can target it
+    pub summary: Option<String>,
+    
+    /// Caller-defined metadata fields (e.g. author, url), stored with the memory and filterable with extra_filters; names may not contain '.', '[' or ']'
+    #[serde(default)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+    
+    /// Return an existing memory of this instance instead of storing when it scores at least DEDUP_THRESHOLD (default: false)
+    #[serde(default)]
+    pub dedup: bool,
+    
+    /// Set this instance's most similar existing memory as parent_id when it scores at least AUTO_LINK_THRESHOLD, returning it as linked_id. An explicit parent_id is kept unless AUTO_LINK_OVERWRITE_PARENT is set (default: false)
+    #[serde(default)]
+    pub auto_link: bool,
+    
+    /// Embed only the first MAX_CONTENT_TOKENS of over-long content instead of rejecting or chunking it; the full content is stored and the result sets embedding_truncated (default: false)
+    #[serde(default)]
+    pub truncate_long_content: bool,
+    
+    /// Embed the content with Markdown syntax (headings, emphasis, link targets, fences) stripped; the content is stored unchanged (default: STRIP_MARKDOWN)
+    pub strip_markdown: Option<bool>,
+    
+    /// Embed the content with HTML tags removed and entities decoded; the content is stored unchanged (default: STRIP_HTML)
+    pub strip_html: Option<bool>,
+    
+    /// Client-chosen key; retrying a store with the same key returns the original result instead of storing again
+    pub idempotency_key: Option<String>,
+    
+    /// OpenAI embedding model for this store instead of EMBEDDING_MODEL; its vectors must have the collection's dimension. Can't be combined with summary when the collection has a summary vector, which is always embedded with EMBEDDING_MODEL
+    pub model: Option<String>,
+    
+    /// Add a per-stage latency breakdown (embedding, redis, qdrant, total) to the result (default: false)
+    #[serde(default)]
+    pub timings: bool,
+    
+    /// Store as this instance instead of the server's INSTANCE_ID: the memory's instance_id, Redis keys, idempotency keys and dedup/auto_link lookups all use it
+    pub instance: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagStoreAndRelateParams {
+    /// Everything rag_store accepts
+    #[serde(flatten)]
+    pub store: RagStoreParams,
+    
+    /// How many related memories to return (default: 5)
+    #[serde(default = "default_neighbors")]
+    pub neighbors: usize,
+    
+    /// Minimum similarity for a related memory (0.0-1.0, default: SIMILARITY_THRESHOLD)
+    pub threshold: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagAggregateParams {
+    /// Field to group by: category, tag, framework or source
+    pub group_by: AggregateField,
+    
+    /// Filter by category
+    pub category_filter: Option<String>,
+    
+    /// Filter by tags (all must match)
+    pub tags_filter: Option<Vec<String>>,
+    
+    /// Filter by instance IDs
+    pub instance_filter: Option<Vec<String>>,
+    
+    /// Only count memories created at or after this RFC 3339 timestamp
+    pub created_after: Option<String>,
+    
+    /// Only count memories created before this RFC 3339 timestamp
+    pub created_before: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagImportVaultParams {
+    /// Path to the vault root directory (on the server)
+    pub path: String,
+    
+    /// Maximum characters per stored chunk (default: 4000)
+    #[serde(default = "default_max_chunk_chars")]
+    pub max_chunk_chars: usize,
+    
+    /// Category for notes without one in their frontmatter
+    pub category: Option<String>,
+    
+    /// Importance score (1-10) for imported memories
+    pub importance: Option<i32>,
+    
+    /// Embed only the first MAX_CONTENT_TOKENS of a chunk that is still too long instead of failing the import; the full chunk is stored (default: false)
+    #[serde(default)]
+    pub truncate_long_content: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagExportCsvParams {
+    /// File path to write the CSV to (on the server)
+    pub path: String,
+    
+    /// Filter by category
+    pub category_filter: Option<String>,
+    
+    /// Filter by tags (all must match)
+    pub tags_filter: Option<Vec<String>>,
+    
+    /// Filter by instance IDs
+    pub instance_filter: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagExportChainParams {
+    /// Chain to export, as given in rag_store's chain_id
+    pub chain_id: String,
+    
+    /// File path to write the Markdown to (on the server); the document is returned inline when omitted
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagClearParams {
+    /// Must equal the instance id being cleared
+    pub confirm: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagDeleteByFilterParams {
+    /// Delete memories in this category
+    pub category_filter: Option<String>,
+    
+    /// How category_filter matches: exact (default) or prefix
+    #[serde(default)]
+    pub category_match_mode: CategoryMatchMode,
+    
+    /// Delete memories carrying these tags (all of them unless tag_min_match is set)
+    pub tags_filter: Option<Vec<String>>,
+    
+    /// Match memories having at least this many of tags_filter (1..=number of tags)
+    pub tag_min_match: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagMigrateModelParams {
+    /// Embedding model to re-embed every memory with
+    pub model: String,
+    
+    /// Collection to create (or resume into) with the new model's dimension; must differ from QDRANT_COLLECTION
+    pub target_collection: String,
+    
+    /// Collection alias to point at the target once every memory migrated; QDRANT_COLLECTION swaps the collection this server uses
+    pub alias: Option<String>,
+    
+    /// Only count the memories and estimate tokens and cost for the model; nothing is embedded, created or indexed (default: false)
+    #[serde(default)]
+    pub dry_run: bool,
+    
+    /// Scroll pages migrated at once (default: MIGRATION_CONCURRENCY); embedding requests stay capped at EMBEDDING_BATCH_CONCURRENCY
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagMergeParams {
+    /// ID of the memory that absorbs the others
+    pub primary_id: String,
+    
+    /// IDs of the memories to fold into the primary; they are deleted afterwards
+    pub ids_to_merge: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagLinkParams {
+    /// ID of one memory to link
+    pub source_id: String,
+    
+    /// ID of the other memory; the link is visible from both
+    pub target_id: String,
+    
+    /// Label for the link, e.g. "supports", "contradicts" or "see_also"
+    pub relation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagUnlinkParams {
+    /// ID of one linked memory
+    pub source_id: String,
+    
+    /// ID of the other linked memory
+    pub target_id: String,
+    
+    /// Only remove the link with this label (default: every link between the two)
+    pub relation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagRelationsParams {
+    /// ID of the memory whose links to list
+    pub id: String,
+    
+    /// Only list links with this label
+    pub relation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagMoveParams {
+    /// ID of the memory to move
+    pub id: String,
+    
+    /// Instance that should own the memory
+    pub target_instance: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagReembedParams {
+    /// ID of the memory to re-embed
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagTagsParams {
+    /// ID of the memory to edit
+    pub id: String,
+    
+    /// Tags to add or remove
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagTagCooccurrenceParams {
+    /// Tag to analyze
+    pub tag: String,
+    
+    /// Maximum number of co-occurring tags to return (default: 20)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RagSuggestTagsParams {
+    /// Content to suggest tags for
+    pub content: String,
+    
+    /// Maximum number of tags to suggest (default: 5)
+    #[serde(default = "default_neighbors")]
+    pub limit: usize,
+    
+    /// How many similar memories to draw tags from (default: 20, max: 100)
+    #[serde(default = "default_limit")]
+    pub candidates: usize,
+    
+    /// Minimum similarity for a memory to contribute its tags (0.0-1.0, default: SIMILARITY_THRESHOLD)
+    pub threshold: Option<f32>,
 }
 
 fn default_limit() -> usize { 20 }
-fn default_threshold() -> f32 { 0.7 }
-fn default_hybrid() -> bool { true }
\ No newline at end of file
+fn default_neighbors() -> usize { 5 }
+fn default_hybrid() -> bool { true }
+fn default_hybrid_alpha() -> f32 { 0.5 }
+fn default_max_chunk_chars() -> usize { 4000 }
+/// Response schema per tool name, derived from the result types the tools serialize.
+/// Tools missing here report a `null` response schema from `rag_schema`.
+pub fn response_schemas() -> serde_json::Map<String, serde_json::Value> {
+    let entries = [
+        ("rag_search", schema_for_type::<SearchResult>()),
+        ("rag_search_by_embedding", schema_for_type::<SearchResult>()),
+        ("rag_get_many", schema_for_type::<GetManyResult>()),
+        ("rag_store", schema_for_type::<StoreResult>()),
+        ("rag_store_and_relate", schema_for_type::<StoreAndRelateResult>()),
+        ("rag_aggregate", schema_for_type::<AggregateResult>()),
+        ("rag_import_vault", schema_for_type::<ImportVaultResult>()),
+        ("rag_export_csv", schema_for_type::<ExportCsvResult>()),
+        ("rag_export_chain", schema_for_type::<ExportChainResult>()),
+        ("rag_clear", schema_for_type::<ClearResult>()),
+        ("rag_delete_by_filter", schema_for_type::<DeleteByFilterResult>()),
+        ("rag_migrate_model", schema_for_type::<MigrateModelResult>()),
+        ("rag_merge", schema_for_type::<MergeResult>()),
+        ("rag_move", schema_for_type::<MoveResult>()),
+        ("rag_reembed", schema_for_type::<ReembedResult>()),
+        ("rag_link", schema_for_type::<LinkResult>()),
+        ("rag_unlink", schema_for_type::<UnlinkResult>()),
+        ("rag_relations", schema_for_type::<RelationsResult>()),
+        ("rag_add_tags", schema_for_type::<TagsResult>()),
+        ("rag_remove_tags", schema_for_type::<TagsResult>()),
+        ("rag_tag_cooccurrence", schema_for_type::<TagCooccurrenceResult>()),
+        ("rag_suggest_tags", schema_for_type::<SuggestTagsResult>()),
+        ("rag_stats", schema_for_type::<CacheStats>()),
+        ("rag_stats_all", schema_for_type::<InstanceStatsResult>()),
+        ("rag_instances", schema_for_type::<InstancesResult>()),
+        ("rag_collection_info", schema_for_type::<CollectionInfoResult>()),
+        ("rag_validate", schema_for_type::<ValidateResult>()),
+        ("rag_count_tokens", schema_for_type::<CountTokensResult>()),
+        ("rag_snapshot", schema_for_type::<SnapshotResult>()),
+        ("rag_list_snapshots", schema_for_type::<SnapshotsResult>()),
+    ];
+    
+    entries
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), serde_json::Value::Object(schema)))
+        .collect()
+}
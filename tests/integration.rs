@@ -0,0 +1,242 @@
+//! End-to-end store -> search -> delete against throwaway Redis and Qdrant containers
+//! started with testcontainers.
+//!
+//! The server binary is driven over MCP stdio, as a client would. OpenAI is never
+//! called: `HASH_EMBEDDINGS` embeds content and queries locally, so plain text
+//! stores and searches go through the same path as real ones.
+//!
+//! Needs a docker daemon, so the test is ignored by default:
+//! `cargo test --test integration -- --ignored`.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+const DIM: usize = 64;
+const INSTANCE: &str = "itest";
+
+/// The server under test, spoken to with newline-delimited JSON-RPC
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl Server {
+    fn spawn(redis_port: u16, qdrant_grpc_port: u16, collection: &str) -> Self {
+        let mut child = tokio::process::Command::new(env!("CARGO_BIN_EXE_unified-rag"))
+            .env("REDIS_HOST", "127.0.0.1")
+            .env("REDIS_PORT", redis_port.to_string())
+            .env("QDRANT_HOST", "127.0.0.1")
+            .env("QDRANT_PORT", qdrant_grpc_port.to_string())
+            .env("QDRANT_COLLECTION", collection)
+            .env("INSTANCE_ID", INSTANCE)
+            .env("HASH_EMBEDDINGS", "true")
+            .env("EMBEDDING_DIMENSION", DIM.to_string())
+            .env_remove("OPENAI_API_KEY")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        Self { child, stdin, stdout, next_id: 1 }
+    }
+
+    async fn send(&mut self, message: Value) {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await.unwrap();
+        self.stdin.flush().await.unwrap();
+    }
+
+    /// Send a request and wait for the response with its id
+    async fn rpc(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})).await;
+
+        let read = async {
+            while let Some(line) = self.stdout.next_line().await.unwrap() {
+                let Ok(response) = serde_json::from_str::<Value>(&line) else { continue };
+                if response["id"] == json!(id) {
+                    return response;
+                }
+            }
+            panic!("server exited before answering {}", method);
+        };
+        // Startup waits for Qdrant, and a store may first try to download its tokenizer
+        tokio::time::timeout(Duration::from_secs(60), read)
+            .await
+            .unwrap_or_else(|_| panic!("no response to {}", method))
+    }
+
+    async fn initialize(&mut self) {
+        self.rpc(
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "itest", "version": "1.0.0"},
+            }),
+        )
+        .await;
+        self.send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await;
+    }
+
+    /// Call a tool and return its JSON result, failing on tool errors
+    async fn call_tool(&mut self, name: &str, arguments: Value) -> Value {
+        let response = self.rpc("tools/call", json!({"name": name, "arguments": arguments})).await;
+        assert!(response.get("error").is_none(), "{} returned an error: {}", name, response["error"]);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        serde_json::from_str(text).unwrap()
+    }
+}
+
+/// Both backends' view of one memory
+struct Backends {
+    redis: redis::aio::MultiplexedConnection,
+    http: reqwest::Client,
+    qdrant_http_port: u16,
+    collection: String,
+}
+
+impl Backends {
+    /// The memory's JSON in Redis, if it is cached there
+    async fn thought(&mut self, id: &str) -> Option<Value> {
+        let json: Option<String> = self.redis.get(format!("{}:Thoughts:{}", INSTANCE, id)).await.unwrap();
+        json.map(|json| serde_json::from_str(&json).unwrap())
+    }
+
+    /// The memory's Qdrant payload, if it has a point
+    async fn payload(&self, id: &str) -> Option<Value> {
+        let url = format!(
+            "http://127.0.0.1:{}/collections/{}/points/{}",
+            self.qdrant_http_port, self.collection, id
+        );
+        let response = self.http.get(url).send().await.unwrap();
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return None;
+        }
+        let point: Value = response.json().await.unwrap();
+        Some(point["result"]["payload"].clone())
+    }
+}
+
+async fn start_redis() -> ContainerAsync<GenericImage> {
+    GenericImage::new("redis", "7")
+        .with_exposed_port(6379.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+        .start()
+        .await
+        .expect("failed to start Redis; is docker running?")
+}
+
+/// Qdrant's log lines vary between versions, so readiness is polled over HTTP instead
+async fn start_qdrant() -> ContainerAsync<GenericImage> {
+    let qdrant = GenericImage::new("qdrant/qdrant", "latest")
+        .with_exposed_port(6333.tcp())
+        .with_exposed_port(6334.tcp())
+        .start()
+        .await
+        .expect("failed to start Qdrant; is docker running?");
+
+    let readyz = format!("http://127.0.0.1:{}/readyz", qdrant.get_host_port_ipv4(6333).await.unwrap());
+    for _ in 0..30 {
+        if reqwest::get(&readyz).await.is_ok_and(|r| r.status().is_success()) {
+            return qdrant;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    panic!("Qdrant did not come up within 30 seconds");
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon; run with --ignored"]
+async fn store_search_delete_round_trip() {
+    let redis_container = start_redis().await;
+    let qdrant_container = start_qdrant().await;
+    let redis_port = redis_container.get_host_port_ipv4(6379).await.unwrap();
+    let qdrant_http_port = qdrant_container.get_host_port_ipv4(6333).await.unwrap();
+    let qdrant_grpc_port = qdrant_container.get_host_port_ipv4(6334).await.unwrap();
+
+    let collection = "unified_rag_itest".to_string();
+    let mut backends = Backends {
+        redis: redis::Client::open(format!("redis://127.0.0.1:{}", redis_port))
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap(),
+        http: reqwest::Client::new(),
+        qdrant_http_port,
+        collection: collection.clone(),
+    };
+    let mut server = Server::spawn(redis_port, qdrant_grpc_port, &collection);
+    server.initialize().await;
+
+    let contents = [
+        ("Qdrant runs the semantic vector search", "keep"),
+        ("Redis caches recent thoughts for fast keyword lookups", "drop"),
+    ];
+    let mut ids = Vec::new();
+    for (content, tag) in contents {
+        let stored = server.call_tool("rag_store", json!({"content": content, "tags": [tag]})).await;
+        assert_eq!(stored["embedding_generated"], json!(true), "store was not embedded: {}", stored);
+        assert_eq!(stored["cached"], json!(true), "memory was not written to Redis: {}", stored);
+        assert_eq!(stored["indexed"], json!(true), "memory was not indexed in Qdrant: {}", stored);
+        ids.push(stored["memory_id"].as_str().unwrap().to_string());
+    }
+    let (kept, dropped) = (&ids[0], &ids[1]);
+
+    // Both backends hold the same memory under the same id and instance
+    for (id, (content, _)) in ids.iter().zip(contents) {
+        let thought = backends.thought(id).await.unwrap_or_else(|| panic!("{} missing from Redis", id));
+        assert_eq!(thought["content"], json!(content));
+        assert_eq!(thought["instance_id"], json!(INSTANCE));
+        let payload = backends.payload(id).await.unwrap_or_else(|| panic!("{} missing from Qdrant", id));
+        assert_eq!(payload["instance_id"], json!(INSTANCE), "wrong Qdrant payload for {}: {}", id, payload);
+        assert_eq!(payload["content"], thought["content"]);
+        assert_eq!(payload["metadata"]["tags"], thought["metadata"]["tags"]);
+    }
+
+    let found = server
+        .call_tool(
+            "rag_search",
+            json!({"query": "semantic vector search", "mode": "semantic_only", "limit": 5, "threshold": 0.0}),
+        )
+        .await;
+    assert_eq!(found["memories"][0]["id"], json!(kept), "search ranked the wrong memory first: {}", found);
+
+    let deleted = server.call_tool("rag_delete_by_filter", json!({"tags_filter": ["drop"]})).await;
+    assert_eq!(deleted["memories_matched"], json!(1), "expected one memory deleted: {}", deleted);
+    assert!(backends.thought(dropped).await.is_none(), "deleted memory still in Redis");
+    assert!(backends.payload(dropped).await.is_none(), "deleted memory still in Qdrant");
+    assert!(backends.thought(kept).await.is_some(), "rag_delete_by_filter removed the wrong memory from Redis");
+    assert!(backends.payload(kept).await.is_some(), "rag_delete_by_filter removed the wrong memory from Qdrant");
+
+    let after = server
+        .call_tool(
+            "rag_search",
+            json!({"query": "redis keyword lookups", "mode": "semantic_only", "limit": 5, "threshold": 0.0}),
+        )
+        .await;
+    let found_ids: Vec<&Value> = after["memories"].as_array().unwrap().iter().map(|m| &m["id"]).collect();
+    assert!(!found_ids.contains(&&json!(dropped)), "search still finds the deleted memory: {}", after);
+
+    let cleared = server.call_tool("rag_clear", json!({"confirm": INSTANCE})).await;
+    assert_eq!(cleared["qdrant_points_removed"], json!(1), "expected one Qdrant point removed: {}", cleared);
+    assert!(backends.thought(kept).await.is_none(), "rag_clear left the memory in Redis");
+    assert!(backends.payload(kept).await.is_none(), "rag_clear left the memory in Qdrant");
+
+    server.child.kill().await.unwrap();
+}
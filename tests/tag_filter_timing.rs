@@ -0,0 +1,174 @@
+//! Timing of the two Redis keyword-search paths for an all-tags filter: `SINTER`
+//! over the tag index sets against a `SCAN` of every thought, each followed by a
+//! `GET` per candidate until `limit` matches, as `RedisCache::search_cached` does.
+//!
+//! The binary crate's cache can't be called from here, so the test replays the same
+//! commands on the same key layout against a throwaway Redis container and prints
+//! the median time of each path per memory count and match density.
+//!
+//! Needs a docker daemon and takes a few minutes, so it is ignored by default:
+//! `cargo test --release --test tag_filter_timing -- --ignored --nocapture`.
+
+use std::time::{Duration, Instant};
+
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::GenericImage;
+
+const INSTANCE: &str = "bench";
+const LIMIT: usize = 20;
+const ROUNDS: usize = 5;
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// One memory in `step` carries both tags
+const STEPS: [usize; 5] = [2, 10, 100, 1_000, 10_000];
+
+fn thought_key(id: &str) -> String {
+    format!("{}:Thoughts:{}", INSTANCE, id)
+}
+
+fn tag_key(tag: &str) -> String {
+    format!("{}:tags:{}", INSTANCE, tag)
+}
+
+/// Half the memories carry `common`; every `step`th also carries `rare`
+fn tags_of(i: usize, step: usize) -> Vec<&'static str> {
+    match (i.is_multiple_of(2), i.is_multiple_of(step)) {
+        (true, true) => vec!["common", "rare"],
+        (true, false) => vec!["common"],
+        _ => vec![],
+    }
+}
+
+/// A thought shaped like a stored `Memory`, so each `GET` moves a realistic payload
+fn thought(i: usize, tags: &[&str]) -> String {
+    json!({
+        "id": format!("00000000-0000-4000-8000-{:012}", i),
+        "instance_id": INSTANCE,
+        "content": format!("Memory {} about caching, search and the tag index. {}", i, "lorem ipsum ".repeat(30)),
+        "metadata": {"tags": tags, "importance": 5, "source": "rag_store", "category": "bench"},
+        "created_at": "2026-01-01T00:00:00Z",
+        "updated_at": "2026-01-01T00:00:00Z",
+        "access_count": 0,
+        "relevance_score": 0.0,
+    })
+    .to_string()
+}
+
+async fn populate(conn: &mut MultiplexedConnection, size: usize, step: usize) {
+    redis::cmd("FLUSHDB").query_async::<()>(conn).await.unwrap();
+    for start in (0..size).step_by(1_000) {
+        let mut pipe = redis::pipe();
+        for i in start..(start + 1_000).min(size) {
+            let id = i.to_string();
+            let tags = tags_of(i, step);
+            pipe.set(thought_key(&id), thought(i, &tags)).ignore();
+            for tag in tags {
+                pipe.sadd(tag_key(tag), &id).ignore();
+            }
+        }
+        pipe.query_async::<()>(conn).await.unwrap();
+    }
+}
+
+fn has_all_tags(json: &str, tags: &[&str]) -> bool {
+    let memory: Value = serde_json::from_str(json).unwrap();
+    let carried = memory["metadata"]["tags"].as_array().unwrap();
+    tags.iter().all(|tag| carried.iter().any(|t| t == tag))
+}
+
+/// Load candidates one `GET` at a time until `LIMIT` of them carry every tag
+async fn take_matches(conn: &mut MultiplexedConnection, ids: &[String], tags: &[&str], found: &mut usize) {
+    for id in ids {
+        if *found >= LIMIT {
+            return;
+        }
+        let json: Option<String> = conn.get(thought_key(id)).await.unwrap();
+        if json.is_some_and(|json| has_all_tags(&json, tags)) {
+            *found += 1;
+        }
+    }
+}
+
+async fn sinter_path(conn: &mut MultiplexedConnection, tags: &[&str]) -> usize {
+    let keys: Vec<String> = tags.iter().map(|tag| tag_key(tag)).collect();
+    let ids: Vec<String> = redis::cmd("SINTER").arg(&keys).query_async(conn).await.unwrap();
+    let mut found = 0;
+    take_matches(conn, &ids, tags, &mut found).await;
+    found
+}
+
+async fn scan_path(conn: &mut MultiplexedConnection, tags: &[&str]) -> usize {
+    let pattern = thought_key("*");
+    let prefix = thought_key("");
+    let mut cursor: u64 = 0;
+    let mut found = 0;
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(100)
+            .query_async(conn)
+            .await
+            .unwrap();
+        let ids: Vec<String> = keys.iter().filter_map(|key| key.strip_prefix(&prefix)).map(str::to_string).collect();
+        take_matches(conn, &ids, tags, &mut found).await;
+        cursor = next;
+        if cursor == 0 || found >= LIMIT {
+            return found;
+        }
+    }
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon and takes minutes; run with --ignored --nocapture"]
+async fn sinter_against_scan_for_all_tags_filters() {
+    let redis = GenericImage::new("redis", "7")
+        .with_exposed_port(6379.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+        .start()
+        .await
+        .expect("failed to start Redis; is docker running?");
+    let port = redis.get_host_port_ipv4(6379).await.unwrap();
+    let mut conn = redis::Client::open(format!("redis://127.0.0.1:{}", port))
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let tags = ["common", "rare"];
+
+    println!("{:>8} {:>9} {:>8} {:>11} {:>11}", "memories", "density", "matches", "sinter_ms", "scan_ms");
+    for size in SIZES {
+        for step in STEPS.into_iter().filter(|&step| step <= size) {
+            populate(&mut conn, size, step).await;
+            let (mut sinter, mut scan) = (Vec::new(), Vec::new());
+            let mut matches = 0;
+            for _ in 0..ROUNDS {
+                let start = Instant::now();
+                matches = sinter_path(&mut conn, &tags).await;
+                sinter.push(start.elapsed());
+
+                let start = Instant::now();
+                assert_eq!(scan_path(&mut conn, &tags).await, matches, "the paths disagree on the matches");
+                scan.push(start.elapsed());
+            }
+            println!(
+                "{:>8} {:>9} {:>8} {:>11.2} {:>11.2}",
+                size,
+                format!("1/{}", step),
+                matches,
+                median(sinter).as_secs_f64() * 1000.0,
+                median(scan).as_secs_f64() * 1000.0,
+            );
+        }
+    }
+}